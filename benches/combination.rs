@@ -3,6 +3,32 @@ use pcf::{BitBoard, Piece::*, PieceSet};
 use std::sync::atomic::AtomicBool;
 
 fn benchmark(c: &mut Criterion) {
+    let empty: BitBoard = BitBoard(0);
+    let pco: BitBoard = "
+        XX____XXXX
+        XXX___XXXX
+        XXXX__XXXX
+        XXX___XXXX
+    "
+    .parse()
+    .unwrap();
+    let grace_system: BitBoard = "
+        ____XXXXXX
+        ____XXXXXX
+        ____XXXXXX
+        ____XXXXXX
+    "
+    .parse()
+    .unwrap();
+    let iszl: BitBoard = "
+        XXXX______
+        XXX_______
+        XXXXX_____
+        XXXX______
+    "
+    .parse()
+    .unwrap();
+
     c.bench_function("2 line com", |b| {
         b.iter(|| {
             pcf::find_combinations(
@@ -29,7 +55,7 @@ fn benchmark(c: &mut Criterion) {
                         .with(O)
                         .with(O),
                 ),
-                black_box(BitBoard::filled(0)),
+                black_box(empty),
                 &AtomicBool::new(false),
                 2,
                 |_| {},
@@ -49,7 +75,7 @@ fn benchmark(c: &mut Criterion) {
                         .with(Z)
                         .with(T),
                 ),
-                black_box(BitBoard(0b1111000011_1111000111_1111001111_1111000111)),
+                black_box(pco),
                 &AtomicBool::new(false),
                 4,
                 |_| {},
@@ -70,7 +96,7 @@ fn benchmark(c: &mut Criterion) {
                         .with(T)
                         .with(T),
                 ),
-                black_box(BitBoard(0b1111110000_1111110000_1111110000_1111110000)),
+                black_box(grace_system),
                 &AtomicBool::new(false),
                 4,
                 |_| {},
@@ -93,7 +119,7 @@ fn benchmark(c: &mut Criterion) {
                         .with(T)
                         .with(T),
                 ),
-                black_box(BitBoard(0b0000001111_0000000111_0000011111_0000001111)),
+                black_box(iszl),
                 &AtomicBool::new(false),
                 4,
                 |_| {},
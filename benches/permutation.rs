@@ -3,11 +3,37 @@ use pcf::{BitBoard, Piece::*};
 use std::sync::atomic::AtomicBool;
 
 fn benchmark(c: &mut Criterion) {
+    let empty: BitBoard = BitBoard(0);
+    let pco: BitBoard = "
+        XX____XXXX
+        XXX___XXXX
+        XXXX__XXXX
+        XXX___XXXX
+    "
+    .parse()
+    .unwrap();
+    let grace_system: BitBoard = "
+        ____XXXXXX
+        ____XXXXXX
+        ____XXXXXX
+        ____XXXXXX
+    "
+    .parse()
+    .unwrap();
+    let iszl: BitBoard = "
+        XXXX______
+        XXX_______
+        XXXXX_____
+        XXXX______
+    "
+    .parse()
+    .unwrap();
+
     c.bench_function("2 line perm", |b| {
         b.iter(|| {
             pcf::solve_pc(
                 black_box(&[I, L, S, J, O, O]),
-                black_box(BitBoard::filled(0)),
+                black_box(empty),
                 true,
                 false,
                 &AtomicBool::new(false),
@@ -20,7 +46,7 @@ fn benchmark(c: &mut Criterion) {
         b.iter(|| {
             pcf::solve_pc(
                 black_box(&[I, T, O, J]),
-                black_box(BitBoard(0b1111000011_1111000111_1111001111_1111000111)),
+                black_box(pco),
                 true,
                 false,
                 &AtomicBool::new(false),
@@ -33,7 +59,7 @@ fn benchmark(c: &mut Criterion) {
         b.iter(|| {
             pcf::solve_pc(
                 black_box(&[T, I, T, O, J]),
-                black_box(BitBoard(0b1111110000_1111110000_1111110000_1111110000)),
+                black_box(grace_system),
                 true,
                 false,
                 &AtomicBool::new(false),
@@ -46,7 +72,7 @@ fn benchmark(c: &mut Criterion) {
         b.iter(|| {
             pcf::solve_pc(
                 black_box(&[J, T, O, L, S, Z, T]),
-                black_box(BitBoard(0b0000001111_0000000111_0000011111_0000001111)),
+                black_box(iszl),
                 true,
                 false,
                 &AtomicBool::new(false),
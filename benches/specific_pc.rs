@@ -8,7 +8,7 @@ fn benchmark(c: &mut Criterion) {
         b.iter(|| {
             pcf::solve_placement_combination(
                 black_box(&[J, L, O, I, T, S, Z, J, L, I, O]),
-                black_box(BitBoard(0)),
+                black_box(BitBoard::<10>(0)),
                 &[
                     Placement {
                         kind: PieceState::IVertical0000,
@@ -63,7 +63,7 @@ fn benchmark(c: &mut Criterion) {
         b.iter(|| {
             pcf::solve_placement_combination(
                 black_box(&[J, L, O, I, T, S, Z, J, L, I, O]),
-                black_box(BitBoard(0)),
+                black_box(BitBoard::<10>(0)),
                 &[
                     Placement {
                         kind: PieceState::IVertical0000,
@@ -118,7 +118,7 @@ fn benchmark(c: &mut Criterion) {
         b.iter(|| {
             pcf::solve_placement_combination(
                 black_box(&[S, Z, O, J, T, L, I, Z, L, J, T]),
-                black_box(BitBoard(0)),
+                black_box(BitBoard::<10>(0)),
                 &[
                     Placement {
                         kind: PieceState::JWest000,
@@ -173,7 +173,7 @@ fn benchmark(c: &mut Criterion) {
         b.iter(|| {
             pcf::solve_placement_combination(
                 black_box(&[S, Z, I, L, T, J, O, T, J, O, Z]),
-                black_box(BitBoard(0)),
+                black_box(BitBoard::<10>(0)),
                 &[
                     Placement {
                         kind: PieceState::O00,
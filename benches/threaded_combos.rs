@@ -3,6 +3,15 @@ use pcf::{BitBoard, Piece::*, PieceSet};
 use std::sync::atomic::AtomicBool;
 
 fn benchmark(c: &mut Criterion) {
+    let iszl: BitBoard = "
+        XXXX______
+        XXX_______
+        XXXXX_____
+        XXXX______
+    "
+    .parse()
+    .unwrap();
+
     let mut threaded = c.benchmark_group("ISZL com");
     threaded.bench_function("1T", |b| {
         rayon::ThreadPoolBuilder::new()
@@ -25,7 +34,7 @@ fn benchmark(c: &mut Criterion) {
                                 .with(T)
                                 .with(T),
                         ),
-                        black_box(BitBoard(0b0000001111_0000000111_0000011111_0000001111)),
+                        black_box(iszl),
                         &AtomicBool::new(false),
                         4,
                         |_| {},
@@ -54,7 +63,7 @@ fn benchmark(c: &mut Criterion) {
                                 .with(T)
                                 .with(T),
                         ),
-                        black_box(BitBoard(0b0000001111_0000000111_0000011111_0000001111)),
+                        black_box(iszl),
                         &AtomicBool::new(false),
                         4,
                         |_| {},
@@ -83,7 +92,7 @@ fn benchmark(c: &mut Criterion) {
                                 .with(T)
                                 .with(T),
                         ),
-                        black_box(BitBoard(0b0000001111_0000000111_0000011111_0000001111)),
+                        black_box(iszl),
                         &AtomicBool::new(false),
                         4,
                         |_| {},
@@ -112,7 +121,7 @@ fn benchmark(c: &mut Criterion) {
                                 .with(T)
                                 .with(T),
                         ),
-                        black_box(BitBoard(0b0000001111_0000000111_0000011111_0000001111)),
+                        black_box(iszl),
                         &AtomicBool::new(false),
                         4,
                         |_| {},
@@ -141,7 +150,7 @@ fn benchmark(c: &mut Criterion) {
                                 .with(T)
                                 .with(T),
                         ),
-                        black_box(BitBoard(0b0000001111_0000000111_0000011111_0000001111)),
+                        black_box(iszl),
                         &AtomicBool::new(false),
                         4,
                         |_| {},
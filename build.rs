@@ -5,6 +5,13 @@ use std::path::Path;
 
 use arrayvec::ArrayVec;
 
+/// Must match `MAX_HEIGHT` in the library.
+///
+/// The generated tables grow quickly with this: at 12 rows `data.rs` is about 1 MB, against
+/// about 100 KB at 6 rows, and compiling the crate takes around 3 seconds longer and twice the
+/// memory.
+const MAX_HEIGHT: u32 = 12;
+
 fn main() -> std::io::Result<()> {
     println!("cargo:rerun-if-changed=build.rs");
 
@@ -141,22 +148,16 @@ fn main() -> std::io::Result<()> {
 
     let mut piece_state_enum =
         "#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)] pub enum PieceState {".to_owned();
-    let mut piece_bits = format!("const PIECE_BITS: &'static [u64; {}] = &[", states.len());
-    let mut piece_widths = format!("const PIECE_WIDTHS: &'static [u8; {}] = &[", states.len());
-    let mut piece_hurdles = format!("const PIECE_HURDLES: &'static [u8; {}] = &[", states.len());
-    let mut piece_below = format!("const PIECE_BELOW: &'static [u64; {}] = &[", states.len());
-    let mut piece_harddrop = format!(
-        "const PIECE_HARDDROP: &'static [u64; {}] = &[",
-        states.len()
-    );
-    let mut piece_y = format!("const PIECE_Y: &'static [u8; {}] = &[", states.len());
-    let mut piece_kinds = format!("const PIECE_KINDS: &'static [Piece; {}] = &[", states.len());
-    let mut piece_srs = format!(
-        "const PIECE_SRS: &'static [&'static [SrsPiece]; {}] = &[",
-        states.len()
-    );
+    let mut piece_bits = format!("pub const PIECE_BITS: &[u128; {}] = &[", states.len());
+    let mut piece_widths = format!("const PIECE_WIDTHS: &[u8; {}] = &[", states.len());
+    let mut piece_hurdles = format!("const PIECE_HURDLES: &[u16; {}] = &[", states.len());
+    let mut piece_below = format!("pub const PIECE_BELOW: &[u128; {}] = &[", states.len());
+    let mut piece_harddrop = format!("pub const PIECE_HARDDROP: &[u128; {}] = &[", states.len());
+    let mut piece_y = format!("const PIECE_Y: &[u8; {}] = &[", states.len());
+    let mut piece_kinds = format!("const PIECE_KINDS: &[Piece; {}] = &[", states.len());
+    let mut piece_srs = format!("const PIECE_SRS: &[&[SrsPiece]; {}] = &[", states.len());
 
-    let mut height_piece_cell_array: [[[String; 6]; 7]; 6] = {
+    let mut height_piece_cell_array: [[[String; 12]; 7]; 12] = {
         let mut a1 = ArrayVec::new();
         a1.extend(std::iter::repeat(String::new()));
         let mut a2 = ArrayVec::new();
@@ -194,23 +195,25 @@ fn main() -> std::io::Result<()> {
         }
         piece_srs.push_str("],");
 
-        for i in data.height as usize - 1..6 {
+        for by_height in &mut height_piece_cell_array[data.height as usize - 1..] {
             let mut y = 0;
             loop {
-                if data.bitboard & 1 << 10 * y != 0 {
+                if data.bitboard & 1 << (10 * y) != 0 {
                     break;
                 }
                 y += 1;
             }
-            let s =
-                &mut height_piece_cell_array[i][piece_index(data.name.chars().next().unwrap())][y];
+            let s = &mut by_height[piece_index(data.name.chars().next().unwrap())][y];
             s.push_str("PieceState::");
             s.push_str(&data.name);
             s.push(',');
         }
     }
 
-    writeln!(file, "pub const PIECE_STATES_BY_HEIGHT_KIND_CELLY: &'static [[[&'static [PieceState]; 6]; 7]; 6] = &[")?;
+    writeln!(
+        file,
+        "pub const PIECE_STATES_BY_HEIGHT_KIND_CELLY: &[[[&[PieceState]; 12]; 7]; 12] = &["
+    )?;
     for h in &height_piece_cell_array {
         writeln!(file, "[")?;
         for p in h {
@@ -244,8 +247,8 @@ fn main() -> std::io::Result<()> {
         stringify! {
             impl PieceState {
                 #[inline]
-                pub fn board(self) -> crate::BitBoard {
-                    BitBoard(PIECE_BITS[self as usize])
+                pub fn board<const W: usize>(self) -> crate::BitBoard<W> {
+                    BitBoard(crate::WidthTables::<W>::PIECE_BITS[self as usize])
                 }
 
                 #[inline]
@@ -254,18 +257,18 @@ fn main() -> std::io::Result<()> {
                 }
 
                 #[inline]
-                pub fn hurdles(self) -> u8 {
+                pub fn hurdles(self) -> u16 {
                     PIECE_HURDLES[self as usize]
                 }
 
                 #[inline]
-                pub fn below_mask(self) -> BitBoard {
-                    BitBoard(PIECE_BELOW[self as usize])
+                pub fn below_mask<const W: usize>(self) -> BitBoard<W> {
+                    BitBoard(crate::WidthTables::<W>::PIECE_BELOW[self as usize])
                 }
 
                 #[inline]
-                pub fn harddrop_mask(self) -> BitBoard {
-                    BitBoard(PIECE_HARDDROP[self as usize])
+                pub fn harddrop_mask<const W: usize>(self) -> BitBoard<W> {
+                    BitBoard(crate::WidthTables::<W>::PIECE_HARDDROP[self as usize])
                 }
 
                 #[inline]
@@ -339,24 +342,24 @@ fn rec_gen_piece_data(
         let mut hdrop = 0;
         for (i, &o) in offsets.iter().enumerate() {
             name.push_str(&o.to_string());
-            bitboard |= (bits[i] as u64) << 10 * (row + o);
-            hdrow |= bits[i] as u64;
-            hdrop |= hdrow << 10 * (row + o);
+            bitboard |= (bits[i] as u128) << (10 * (row + o));
+            hdrow |= bits[i] as u128;
+            hdrop |= hdrow << (10 * (row + o));
             if row != 0 {
-                below |= (bits[i] as u64) << 10 * (row - 1);
+                below |= (bits[i] as u128) << (10 * (row - 1));
             }
             if i == 0 && o != 0 {
-                below |= (bits[i] as u64) << 10 * (o - 1);
+                below |= (bits[i] as u128) << (10 * (o - 1));
             }
             if i != 0 {
                 for j in 0..o {
-                    hurdles |= 1 << row + j;
+                    hurdles |= 1 << (row + j);
                 }
             }
             row += o + 1;
         }
-        for i in row..6 {
-            hdrop |= hdrow << 10 * i;
+        for i in row..MAX_HEIGHT {
+            hdrop |= hdrow << (10 * i);
         }
         data.push(PieceData {
             name,
@@ -374,7 +377,7 @@ fn rec_gen_piece_data(
         });
         return;
     }
-    let remaining = 6 - offsets.iter().copied().sum::<u32>() - h;
+    let remaining = MAX_HEIGHT - offsets.iter().copied().sum::<u32>() - h;
     for offset in 0..=remaining {
         offsets.push(offset);
         rec_gen_piece_data(data, bits, w, h, name, offsets, rots);
@@ -389,8 +392,8 @@ struct PieceData {
     y: u32,
     width: u32,
     height: u32,
-    bitboard: u64,
-    below: u64,
-    harddrop: u64,
+    bitboard: u128,
+    below: u128,
+    harddrop: u128,
     srs: Vec<(&'static str, i32, u32)>,
 }
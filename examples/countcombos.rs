@@ -10,7 +10,7 @@ fn main() {
     let mut incr = DelayedIncrement::new(&count);
     pcf::find_combinations(
         pieces,
-        pcf::BitBoard(0),
+        pcf::BitBoard::<10>(0),
         &Default::default(),
         4,
         move |_| {
@@ -31,7 +31,7 @@ struct DelayedIncrement<'a> {
 }
 
 impl DelayedIncrement<'_> {
-    pub fn new(target: &AtomicU64) -> DelayedIncrement {
+    pub fn new(target: &AtomicU64) -> DelayedIncrement<'_> {
         DelayedIncrement { local: 0, target }
     }
 
@@ -1,10 +1,8 @@
-use fumen::Fumen;
+use pcf::fumen::{self, Fumen};
 use pcf::{placeability, BitBoard, Piece};
 use rand::prelude::*;
 use std::sync::atomic::AtomicBool;
 
-mod common;
-
 fn main() {
     let mut queue = [
         Piece::I,
@@ -34,12 +32,12 @@ fn main() {
     let t = std::time::Instant::now();
     pcf::solve_pc_mt(
         &queue,
-        BitBoard(0),
+        BitBoard::<10>(0),
         true,
         true,
         &AtomicBool::new(false),
         placeability::tucks,
-        move |soln| common::add_placement_pages(&mut fumen, BitBoard(0), soln),
+        move |soln| fumen::add_solution_pages(&mut fumen, BitBoard(0), soln),
     );
     println!("Done in {:?}.", t.elapsed());
 
@@ -1,12 +1,17 @@
-use fumen::{CellColor, Fumen};
+use pcf::fumen::{self, Fumen};
 use pcf::{BitBoard, Piece, PieceSet};
 use rand::prelude::*;
 use std::sync::atomic::AtomicBool;
 
-mod common;
-
 fn main() {
-    let board = BitBoard(0b1100001111_1110001111_1111001111_1110001111);
+    let board: BitBoard = "
+        XXXX____XX
+        XXXX___XXX
+        XXXX__XXXX
+        XXXX___XXX
+    "
+    .parse()
+    .unwrap();
     let pieces = PieceSet::default()
         .with(Piece::I)
         .with(Piece::T)
@@ -17,12 +22,10 @@ fn main() {
         .with(Piece::J);
 
     let mut fumen = Fumen::default();
-    common::blit(&mut fumen.pages[0], board, CellColor::Grey);
+    fumen.pages.push(fumen::combination_page(board, &[]));
 
     pcf::find_combinations(pieces, board, &AtomicBool::new(false), 4, |combo| {
-        let mut page = fumen.pages[0].clone();
-        common::draw_placements(&mut page, &combo);
-        fumen.pages.push(page);
+        fumen.pages.push(fumen::combination_page(board, combo));
     });
 
     println!(
@@ -50,7 +53,7 @@ fn main() {
         true,
         &AtomicBool::new(false),
         pcf::placeability::simple_srs_spins,
-        |soln| common::add_placement_pages(&mut fumen, board, soln),
+        |soln| fumen::add_solution_pages(&mut fumen, board, soln),
     );
 
     println!(
@@ -1,10 +1,8 @@
-use fumen::Fumen;
+use pcf::fumen::{self, Fumen};
 use pcf::{placeability, BitBoard, Piece};
 use rand::prelude::*;
 use std::sync::atomic::AtomicBool;
 
-mod common;
-
 fn main() {
     let mut queue = [
         Piece::I,
@@ -32,7 +30,16 @@ fn main() {
     let (send, recv) = std::sync::mpsc::channel();
     let mut fumen = SendOnDrop::new(send, fumen);
     let t = std::time::Instant::now();
-    let b = BitBoard(0b1000000000_1000000000_1000000000_1000000000_1100000000_1100000000);
+    let b: BitBoard = "
+        _________X
+        _________X
+        _________X
+        _________X
+        ________XX
+        ________XX
+    "
+    .parse()
+    .unwrap();
     pcf::solve_pc_mt(
         &queue,
         b,
@@ -40,7 +47,7 @@ fn main() {
         true,
         &AtomicBool::new(false),
         placeability::hard_drop_only,
-        move |soln| common::add_placement_pages(&mut fumen, b, soln),
+        move |soln| fumen::add_solution_pages(&mut fumen, b, soln),
     );
     println!("Done in {:?}.", t.elapsed());
 
@@ -0,0 +1,242 @@
+use crate::randomizer::{pc_chance, Randomizer};
+use crate::*;
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+
+/// A sequence of perfect clears in a row, as found by `plan_pc_chain`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct PcChain {
+    /// One solution for each PC, in order. The first is on the starting board and the rest are
+    /// on an empty board.
+    pub solutions: Vec<Vec<Placement>>,
+    /// The queue left after the last PC, with the held piece, if any, first.
+    pub leftover: Vec<Piece>,
+}
+
+/// The queue left after placing a solution found for `queue`, with the piece that ends up in
+/// hold first.
+///
+/// Holding a piece is the same as moving it to the front of the queue for the next solve, so
+/// this is the queue the next PC has to work with.
+pub fn leftover_queue(queue: &[Piece], solution: &[Placement]) -> Vec<Piece> {
+    let info = SolutionInfo::from_solution(queue, solution);
+    info.hold.into_iter().chain(info.remaining).collect()
+}
+
+/// Finds the longest chain of perfect clears the queue can do, up to `max_pcs`, starting from
+/// `board` and continuing from an empty board after each PC.
+///
+/// Among chains with the same number of PCs, the first one found is returned.
+pub fn plan_pc_chain<const W: usize>(
+    queue: &[Piece],
+    board: BitBoard<W>,
+    hold_allowed: bool,
+    max_pcs: usize,
+    abort: &AtomicBool,
+    placeability_judge: impl Fn(BitBoard<W>, Placement) -> bool,
+) -> PcChain {
+    chain(
+        queue,
+        board,
+        hold_allowed,
+        max_pcs,
+        abort,
+        &placeability_judge,
+        &mut HashMap::new(),
+    )
+}
+
+fn chain<const W: usize>(
+    queue: &[Piece],
+    board: BitBoard<W>,
+    hold_allowed: bool,
+    max_pcs: usize,
+    abort: &AtomicBool,
+    placeability_judge: &impl Fn(BitBoard<W>, Placement) -> bool,
+    memo: &mut HashMap<(Vec<Piece>, usize), PcChain>,
+) -> PcChain {
+    let mut best = PcChain {
+        solutions: vec![],
+        leftover: queue.to_vec(),
+    };
+    if max_pcs == 0 {
+        return best;
+    }
+    if board == BitBoard(0) {
+        if let Some(chain) = memo.get(&(queue.to_vec(), max_pcs)) {
+            return chain.clone();
+        }
+    }
+
+    // only one solution is needed for each distinct leftover queue
+    let mut by_leftover: Vec<(Vec<Piece>, Vec<Placement>)> = vec![];
+    solve_pc(
+        queue,
+        board,
+        hold_allowed,
+        true,
+        abort,
+        placeability_judge,
+        |soln| {
+            let leftover = leftover_queue(queue, soln);
+            if by_leftover.iter().all(|(l, _)| *l != leftover) {
+                by_leftover.push((leftover, soln.to_vec()));
+            }
+        },
+    );
+
+    for (leftover, solution) in by_leftover {
+        let rest = chain(
+            &leftover,
+            BitBoard(0),
+            hold_allowed,
+            max_pcs - 1,
+            abort,
+            placeability_judge,
+            memo,
+        );
+        if rest.solutions.len() + 1 > best.solutions.len() {
+            best.solutions = std::iter::once(solution).chain(rest.solutions).collect();
+            best.leftover = rest.leftover;
+        }
+    }
+
+    if board == BitBoard(0) {
+        memo.insert((queue.to_vec(), max_pcs), best.clone());
+    }
+    best
+}
+
+/// Finds the solutions of the first PC and ranks them by the chance of a second PC from an
+/// empty board, best first.
+///
+/// The second PC uses a queue of `next_length` pieces: the leftover queue followed by pieces
+/// from `randomizer`, which should be in the state after dealing the last piece of `queue`.
+/// Each entry has the solution, its leftover queue and the chance of the next PC.
+pub fn rank_by_next_pc<const W: usize>(
+    queue: &[Piece],
+    board: BitBoard<W>,
+    hold_allowed: bool,
+    randomizer: &impl Randomizer,
+    next_length: usize,
+    abort: &AtomicBool,
+    placeability_judge: impl Fn(BitBoard<W>, Placement) -> bool,
+) -> Vec<(Vec<Placement>, Vec<Piece>, f64)> {
+    let mut solutions = vec![];
+    solve_pc(
+        queue,
+        board,
+        hold_allowed,
+        true,
+        abort,
+        &placeability_judge,
+        |soln| solutions.push(soln.to_vec()),
+    );
+
+    let mut chances = HashMap::new();
+    let mut ranked: Vec<_> = solutions
+        .into_iter()
+        .map(|solution| {
+            let leftover = leftover_queue(queue, &solution);
+            let chance = *chances.entry(leftover.clone()).or_insert_with(|| {
+                pc_chance(
+                    BitBoard(0),
+                    &leftover,
+                    randomizer,
+                    next_length,
+                    hold_allowed,
+                    abort,
+                    &placeability_judge,
+                )
+            });
+            (solution, leftover, chance)
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.2.total_cmp(&a.2));
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::randomizer::Memoryless;
+    use Piece::*;
+
+    fn two_lines() -> BitBoard {
+        "
+        XXXX______
+        XXXX______
+        "
+        .parse()
+        .unwrap()
+    }
+
+    #[test]
+    fn chains_pcs_from_an_empty_board() {
+        let queue = [O, I, I, I, I, I, I, O, T];
+        let plan = |max_pcs| {
+            plan_pc_chain(
+                &queue,
+                two_lines(),
+                false,
+                max_pcs,
+                &AtomicBool::new(false),
+                placeability::always,
+            )
+        };
+
+        // the second PC is two lines on an empty board: four Is and an O
+        let chain = plan(3);
+        assert_eq!(chain.solutions.len(), 2);
+        assert_eq!(chain.solutions[0].len(), 3);
+        assert_eq!(chain.solutions[1].len(), 5);
+        assert_eq!(chain.leftover, vec![T]);
+
+        let first = plan(1);
+        assert_eq!(first.solutions.len(), 1);
+        assert_eq!(first.leftover, vec![I, I, I, I, O, T]);
+        assert_eq!(
+            plan(0),
+            PcChain {
+                solutions: vec![],
+                leftover: queue.to_vec(),
+            }
+        );
+    }
+
+    #[test]
+    fn ranks_solutions_by_the_next_pc() {
+        // every solution leaves four Is, so the next PC needs an O
+        let ranked = rank_by_next_pc(
+            &[O, I, I, I, I, I, I],
+            two_lines(),
+            false,
+            &Memoryless,
+            5,
+            &AtomicBool::new(false),
+            placeability::always,
+        );
+        assert!(!ranked.is_empty());
+        for (solution, leftover, chance) in &ranked {
+            assert_eq!(solution.len(), 3);
+            assert_eq!(leftover, &[I, I, I, I]);
+            assert!((chance - 1.0 / 7.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn leftover_puts_hold_first() {
+        let board = two_lines();
+        let mut solution = None;
+        solve_pc(
+            &[T, O, I, I],
+            board,
+            true,
+            true,
+            &AtomicBool::new(false),
+            placeability::always,
+            |soln| solution = Some(soln.to_vec()),
+        );
+        assert_eq!(leftover_queue(&[T, O, I, I], &solution.unwrap()), vec![T]);
+    }
+}
@@ -1,28 +1,23 @@
+use crate::polyomino::{self, PieceTable};
 use crate::*;
 use std::sync::atomic::{AtomicBool, Ordering};
 
-pub fn find_combinations(
+pub fn find_combinations<const W: usize>(
     piece_set: PieceSet,
-    board: BitBoard,
+    board: BitBoard<W>,
     abort: &AtomicBool,
     height: usize,
     mut combo_consumer: impl FnMut(&[Placement]),
 ) {
-    find_combos_st(
-        &mut vec![],
-        board,
-        BitBoard::filled(height),
-        piece_set,
-        abort,
-        height,
-        &|_, _, _, _, _| true,
-        &mut combo_consumer,
-    );
+    let mut combos = CombinationIter::new(piece_set, board, abort, height);
+    while let Some(combo) = combos.next_combination() {
+        combo_consumer(combo);
+    }
 }
 
-pub fn find_combinations_mt(
+pub fn find_combinations_mt<const W: usize>(
     piece_set: PieceSet,
-    board: BitBoard,
+    board: BitBoard<W>,
     abort: &AtomicBool,
     height: usize,
     combo_consumer: impl FnMut(&[Placement]) + Clone + Send,
@@ -30,6 +25,7 @@ pub fn find_combinations_mt(
     rayon::scope(|scope| {
         find_combos_mt(
             scope,
+            Tetrominoes,
             vec![],
             board,
             BitBoard::filled(height),
@@ -37,43 +33,38 @@ pub fn find_combinations_mt(
             abort,
             height,
             0,
-            |_, _, _, _, _| true,
+            NoPruning,
             combo_consumer,
         )
     });
 }
 
-pub fn find_combinations_with_pruning(
+pub fn find_combinations_with_pruning<const W: usize>(
     piece_set: PieceSet,
-    board: BitBoard,
+    board: BitBoard<W>,
     abort: &AtomicBool,
     height: usize,
-    is_ok: impl Fn(&[Placement], BitBoard, BitBoard, usize, PieceSet) -> bool,
+    is_ok: impl Fn(&[Placement], BitBoard<W>, BitBoard<W>, usize, PieceSet) -> bool,
     mut combo_consumer: impl FnMut(&[Placement]),
 ) {
-    find_combos_st(
-        &mut vec![],
-        board,
-        BitBoard::filled(height),
-        piece_set,
-        abort,
-        height,
-        &is_ok,
-        &mut combo_consumer,
-    );
+    let mut combos = CombinationIter::with_pruning(piece_set, board, abort, height, is_ok);
+    while let Some(combo) = combos.next_combination() {
+        combo_consumer(combo);
+    }
 }
 
-pub fn find_combinations_with_pruning_mt(
+pub fn find_combinations_with_pruning_mt<const W: usize>(
     piece_set: PieceSet,
-    board: BitBoard,
+    board: BitBoard<W>,
     abort: &AtomicBool,
     height: usize,
-    is_ok: impl Fn(&[Placement], BitBoard, BitBoard, usize, PieceSet) -> bool + Clone + Send,
+    is_ok: impl Fn(&[Placement], BitBoard<W>, BitBoard<W>, usize, PieceSet) -> bool + Clone + Send,
     combo_consumer: impl FnMut(&[Placement]) + Clone + Send,
 ) {
     rayon::scope(|scope| {
         find_combos_mt(
             scope,
+            Tetrominoes,
             vec![],
             board,
             BitBoard::filled(height),
@@ -87,71 +78,500 @@ pub fn find_combinations_with_pruning_mt(
     });
 }
 
-fn find_combos_st(
-    placements: &mut Vec<Placement>,
-    board: BitBoard,
-    inverse_placed: BitBoard,
-    piece_set: PieceSet,
-    abort: &AtomicBool,
-    height: usize,
-    is_ok: &impl Fn(&[Placement], BitBoard, BitBoard, usize, PieceSet) -> bool,
-    combo_consumer: &mut impl FnMut(&[Placement]),
-) {
-    find_combos(
-        board,
-        inverse_placed,
-        piece_set,
-        abort,
-        height,
-        |placement, board, inverse_placed, piece_set| {
-            placements.push(placement);
-            if has_cyclic_dependency(inverse_placed, placements, height) {
-            } else if board == BitBoard::filled(height) {
-                combo_consumer(placements);
-            } else if !vertical_parity_ok(board, piece_set, height) {
-            } else if !is_ok(&placements, board, inverse_placed, height, piece_set) {
+/// Decides whether the search for combinations continues past a partial combination, given the
+/// same arguments as the `is_ok` of `find_combinations_with_pruning`. Implemented for every
+/// closure that takes them.
+pub trait Pruning<const W: usize> {
+    fn is_ok(
+        &self,
+        placements: &[Placement],
+        board: BitBoard<W>,
+        inverse_placed: BitBoard<W>,
+        height: usize,
+        piece_set: PieceSet,
+    ) -> bool;
+}
+
+impl<const W: usize, F> Pruning<W> for F
+where
+    F: Fn(&[Placement], BitBoard<W>, BitBoard<W>, usize, PieceSet) -> bool,
+{
+    #[inline(always)]
+    fn is_ok(
+        &self,
+        placements: &[Placement],
+        board: BitBoard<W>,
+        inverse_placed: BitBoard<W>,
+        height: usize,
+        piece_set: PieceSet,
+    ) -> bool {
+        self(placements, board, inverse_placed, height, piece_set)
+    }
+}
+
+/// Pruning that accepts every partial combination, used when searching without `is_ok`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NoPruning;
+
+impl<const W: usize> Pruning<W> for NoPruning {
+    #[inline(always)]
+    fn is_ok(
+        &self,
+        _: &[Placement],
+        _: BitBoard<W>,
+        _: BitBoard<W>,
+        _: usize,
+        _: PieceSet,
+    ) -> bool {
+        true
+    }
+}
+
+/// Pruning for the combinations of any kind of piece. Every `Pruning` prunes tetromino
+/// combinations, while combinations of other pieces are never pruned.
+pub(crate) trait Prune<const W: usize, K: PieceKinds<W>> {
+    fn keep(
+        &self,
+        placements: &[K::Placement],
+        board: BitBoard<W>,
+        inverse_placed: BitBoard<W>,
+        height: usize,
+        piece_set: &K::Set,
+    ) -> bool;
+}
+
+impl<const W: usize, P: Pruning<W>> Prune<W, Tetrominoes> for P {
+    #[inline(always)]
+    fn keep(
+        &self,
+        placements: &[Placement],
+        board: BitBoard<W>,
+        inverse_placed: BitBoard<W>,
+        height: usize,
+        piece_set: &PieceSet,
+    ) -> bool {
+        self.is_ok(placements, board, inverse_placed, height, *piece_set)
+    }
+}
+
+impl<const W: usize> Prune<W, &PieceTable<W>> for NoPruning {
+    #[inline(always)]
+    fn keep(
+        &self,
+        _: &[polyomino::Placement<W>],
+        _: BitBoard<W>,
+        _: BitBoard<W>,
+        _: usize,
+        _: &Vec<u8>,
+    ) -> bool {
+        true
+    }
+}
+
+/// The pieces that combinations and permutations are made of: the seven `Tetrominoes`, whose
+/// tables are generated at build time, or the pieces of a `PieceTable`, whose tables are built
+/// at runtime.
+pub(crate) trait PieceKinds<const W: usize>: Copy {
+    type Piece: Copy + PartialEq;
+    type Placement: Copy;
+    /// How many of each piece are left.
+    type Set: Clone;
+
+    fn piece(self, placement: Self::Placement) -> Self::Piece;
+
+    fn board(self, placement: Self::Placement) -> BitBoard<W>;
+
+    fn supported_after_clears(self, placement: Self::Placement, on: BitBoard<W>) -> bool;
+
+    fn supported_without_clears(self, placement: Self::Placement, on: BitBoard<W>) -> bool;
+
+    fn without(self, piece_set: &Self::Set, piece: Self::Piece) -> Self::Set;
+
+    /// The next placement after those `cursor` has passed that fills the cell at `x`, `y` with
+    /// the lowest cell of its leftmost column, using a piece left in `piece_set`. It must lie
+    /// within the lowest `height` rows and not overlap `board`.
+    fn next_placement(
+        self,
+        piece_set: &Self::Set,
+        board: BitBoard<W>,
+        height: usize,
+        x: usize,
+        y: usize,
+        cursor: &mut Cursor,
+    ) -> Option<Self::Placement>;
+
+    /// Checks that the pieces left can fill the rest of the board, beyond there being the
+    /// right number of cells.
+    fn can_fill(self, board: BitBoard<W>, piece_set: &Self::Set, height: usize) -> bool;
+}
+
+/// How far the search through the placements that fill a cell has gotten.
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct Cursor {
+    /// Index of the piece being tried.
+    pub(crate) piece: usize,
+    /// Index of the state of the piece being tried.
+    pub(crate) state: usize,
+}
+
+/// The seven standard pieces.
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct Tetrominoes;
+
+impl Tetrominoes {
+    /// Number of cells in each piece.
+    pub(crate) const CELLS: usize = 4;
+}
+
+impl<const W: usize> PieceKinds<W> for Tetrominoes {
+    type Piece = Piece;
+    type Placement = Placement;
+    type Set = PieceSet;
+
+    #[inline(always)]
+    fn piece(self, placement: Placement) -> Piece {
+        placement.kind.piece()
+    }
+
+    #[inline(always)]
+    fn board(self, placement: Placement) -> BitBoard<W> {
+        placement.board()
+    }
+
+    #[inline(always)]
+    fn supported_after_clears(self, placement: Placement, on: BitBoard<W>) -> bool {
+        placement.supported_after_clears(on)
+    }
+
+    #[inline(always)]
+    fn supported_without_clears(self, placement: Placement, on: BitBoard<W>) -> bool {
+        placement.supported_without_clears(on)
+    }
+
+    #[inline(always)]
+    fn without(self, piece_set: &PieceSet, piece: Piece) -> PieceSet {
+        piece_set.without(piece)
+    }
+
+    #[inline(always)]
+    fn next_placement(
+        self,
+        piece_set: &PieceSet,
+        board: BitBoard<W>,
+        height: usize,
+        x: usize,
+        y: usize,
+        cursor: &mut Cursor,
+    ) -> Option<Placement> {
+        while let Some(&piece) = PIECES.get(cursor.piece) {
+            let states: &[PieceState] = if piece_set.contains(piece) {
+                crate::data::PIECE_STATES_BY_HEIGHT_KIND_CELLY[height - 1][piece as usize][y]
             } else {
-                find_combos_st(
-                    placements,
+                // this piece can't be used again
+                &[]
+            };
+            while let Some(&piece_state) = states.get(cursor.state) {
+                cursor.state += 1;
+                if x + piece_state.width() as usize > W {
+                    // piece doesn't fit. array is sorted by width, so all future states fail
+                    // this too.
+                    break;
+                }
+                let placement = Placement {
+                    kind: piece_state,
+                    x: x as u8,
+                };
+                if !placement.board().overlaps(board) {
+                    return Some(placement);
+                }
+            }
+            cursor.piece += 1;
+            cursor.state = 0;
+        }
+        None
+    }
+
+    #[inline(always)]
+    fn can_fill(self, board: BitBoard<W>, piece_set: &PieceSet, height: usize) -> bool {
+        vertical_parity_ok(board, *piece_set, height)
+    }
+}
+
+/// Finds the same combinations as `find_combinations`, but one at a time as they're asked for.
+///
+/// The search stack is kept in the iterator, so the search can be stopped after any number of
+/// combinations, or paused and picked up again later. `next_combination` gives each combination
+/// without allocating; the `Iterator` impl gives each one as a `Vec`.
+pub struct CombinationIter<'a, const W: usize = 10, P = NoPruning> {
+    combinations: Combinations<'a, W, Tetrominoes, P>,
+}
+
+impl<'a, const W: usize> CombinationIter<'a, W> {
+    pub fn new(
+        piece_set: PieceSet,
+        board: BitBoard<W>,
+        abort: &'a AtomicBool,
+        height: usize,
+    ) -> Self {
+        CombinationIter::search(piece_set, board, abort, height, NoPruning)
+    }
+}
+
+impl<'a, const W: usize, P> CombinationIter<'a, W, P>
+where
+    P: Fn(&[Placement], BitBoard<W>, BitBoard<W>, usize, PieceSet) -> bool,
+{
+    /// Like `new`, but the search doesn't continue past partial combinations that `is_ok`
+    /// rejects, like `find_combinations_with_pruning`.
+    pub fn with_pruning(
+        piece_set: PieceSet,
+        board: BitBoard<W>,
+        abort: &'a AtomicBool,
+        height: usize,
+        is_ok: P,
+    ) -> Self {
+        CombinationIter::search(piece_set, board, abort, height, is_ok)
+    }
+}
+
+impl<'a, const W: usize, P: Pruning<W>> CombinationIter<'a, W, P> {
+    pub(crate) fn search(
+        piece_set: PieceSet,
+        board: BitBoard<W>,
+        abort: &'a AtomicBool,
+        height: usize,
+        is_ok: P,
+    ) -> Self {
+        CombinationIter {
+            combinations: Combinations::new(Tetrominoes, piece_set, board, abort, height, is_ok),
+        }
+    }
+
+    /// Finds the next combination, or returns `None` once there are no more.
+    pub fn next_combination(&mut self) -> Option<&[Placement]> {
+        self.combinations.next_combination()
+    }
+}
+
+impl<const W: usize, P: Pruning<W>> Iterator for CombinationIter<'_, W, P> {
+    type Item = Vec<Placement>;
+
+    fn next(&mut self) -> Option<Vec<Placement>> {
+        self.next_combination().map(<[_]>::to_vec)
+    }
+}
+
+/// The search behind `CombinationIter`, for any kind of piece.
+pub(crate) struct Combinations<'a, const W: usize, K: PieceKinds<W>, P> {
+    kinds: K,
+    stack: Vec<ComboFrame<W, K>>,
+    placements: Vec<K::Placement>,
+    /// Whether the last placement completed a combination that was already given out.
+    found: bool,
+    abort: &'a AtomicBool,
+    height: usize,
+    is_ok: P,
+}
+
+impl<'a, const W: usize, K: PieceKinds<W>, P: Prune<W, K>> Combinations<'a, W, K, P> {
+    pub(crate) fn new(
+        kinds: K,
+        piece_set: K::Set,
+        board: BitBoard<W>,
+        abort: &'a AtomicBool,
+        height: usize,
+        is_ok: P,
+    ) -> Self {
+        Self::resume(
+            kinds,
+            vec![],
+            board,
+            BitBoard::filled(height),
+            piece_set,
+            abort,
+            height,
+            is_ok,
+        )
+    }
+
+    /// Continues the search from a partial combination.
+    #[allow(clippy::too_many_arguments)]
+    fn resume(
+        kinds: K,
+        placements: Vec<K::Placement>,
+        board: BitBoard<W>,
+        inverse_placed: BitBoard<W>,
+        piece_set: K::Set,
+        abort: &'a AtomicBool,
+        height: usize,
+        is_ok: P,
+    ) -> Self {
+        Combinations {
+            kinds,
+            stack: vec![ComboFrame::new(board, inverse_placed, piece_set, height)],
+            placements,
+            found: false,
+            abort,
+            height,
+            is_ok,
+        }
+    }
+
+    /// Finds the next combination, or returns `None` once there are no more.
+    pub(crate) fn next_combination(&mut self) -> Option<&[K::Placement]> {
+        if self.advance() {
+            Some(&self.placements)
+        } else {
+            None
+        }
+    }
+
+    fn advance(&mut self) -> bool {
+        if self.found {
+            self.found = false;
+            self.placements.pop();
+        }
+        while let Some(frame) = self.stack.last_mut() {
+            // Check if we should abort the search
+            if self.abort.load(Ordering::Acquire) {
+                self.stack.clear();
+                return false;
+            }
+            let placement = match frame.next_placement(self.kinds, self.height) {
+                Some(placement) => placement,
+                None => {
+                    // every placement from this frame has been tried, so undo the placement
+                    // that led to it
+                    self.stack.pop();
+                    if !self.stack.is_empty() {
+                        self.placements.pop();
+                    }
+                    continue;
+                }
+            };
+
+            let (board, inverse_placed, piece_set) = frame.place(self.kinds, placement);
+
+            self.placements.push(placement);
+            if has_cyclic_dependency(self.kinds, inverse_placed, &self.placements, self.height) {
+                // no order of these placements can be placed
+            } else if board == BitBoard::filled(self.height) {
+                self.found = true;
+                return true;
+            } else if self.kinds.can_fill(board, &piece_set, self.height)
+                && self.is_ok.keep(
+                    &self.placements,
                     board,
                     inverse_placed,
-                    piece_set,
-                    abort,
-                    height,
-                    is_ok,
-                    combo_consumer,
+                    self.height,
+                    &piece_set,
                 )
-            };
-            placements.pop();
-        },
-    )
+            {
+                self.stack.push(ComboFrame::new(
+                    board,
+                    inverse_placed,
+                    piece_set,
+                    self.height,
+                ));
+                continue;
+            }
+            self.placements.pop();
+        }
+        false
+    }
+}
+
+/// A partial combination on the search stack, and the next placement to try on it.
+struct ComboFrame<const W: usize, K: PieceKinds<W>> {
+    board: BitBoard<W>,
+    inverse_placed: BitBoard<W>,
+    piece_set: K::Set,
+    /// The lowest empty cell of the leftmost column with empty cells.
+    x: usize,
+    y: usize,
+    cursor: Cursor,
 }
 
-fn find_combos_mt<'s>(
+impl<const W: usize, K: PieceKinds<W>> ComboFrame<W, K> {
+    fn new(
+        board: BitBoard<W>,
+        inverse_placed: BitBoard<W>,
+        piece_set: K::Set,
+        height: usize,
+    ) -> Self {
+        let x = board.leftmost_empty_column(height);
+        let y = (0..height).find(|&y| !board.cell_filled(x, y)).unwrap_or(0);
+        ComboFrame {
+            board,
+            inverse_placed,
+            piece_set,
+            x,
+            y,
+            cursor: Cursor::default(),
+        }
+    }
+
+    /// The next placement that fills the cell at `x`, `y`.
+    #[inline(always)]
+    fn next_placement(&mut self, kinds: K, height: usize) -> Option<K::Placement> {
+        kinds.next_placement(
+            &self.piece_set,
+            self.board,
+            height,
+            self.x,
+            self.y,
+            &mut self.cursor,
+        )
+    }
+
+    /// The board, inverse placed board and piece set after the placement is made.
+    #[inline(always)]
+    fn place(&self, kinds: K, placement: K::Placement) -> (BitBoard<W>, BitBoard<W>, K::Set) {
+        let piece_board = kinds.board(placement);
+        (
+            piece_board.combine(self.board),
+            self.inverse_placed.remove(piece_board),
+            kinds.without(&self.piece_set, kinds.piece(placement)),
+        )
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn find_combos_mt<'s, const W: usize, K, P>(
     scope: &rayon::Scope<'s>,
-    mut placements: Vec<Placement>,
-    board: BitBoard,
-    inverse_placed: BitBoard,
-    piece_set: PieceSet,
+    kinds: K,
+    mut placements: Vec<K::Placement>,
+    board: BitBoard<W>,
+    inverse_placed: BitBoard<W>,
+    piece_set: K::Set,
     abort: &'s AtomicBool,
     height: usize,
     recursions: usize,
-    is_ok: impl Fn(&[Placement], BitBoard, BitBoard, usize, PieceSet) -> bool + Clone + Send + 's,
-    mut combo_consumer: impl FnMut(&[Placement]) + Clone + Send + 's,
-) {
+    is_ok: P,
+    mut combo_consumer: impl FnMut(&[K::Placement]) + Clone + Send + 's,
+) where
+    K: PieceKinds<W> + Send + 's,
+    K::Placement: Send,
+    K::Set: Send,
+    P: Prune<W, K> + Clone + Send + 's,
+{
     if recursions >= 3 {
-        find_combos_st(
-            &mut placements,
+        let mut combos = Combinations::resume(
+            kinds,
+            placements,
             board,
             inverse_placed,
             piece_set,
             abort,
             height,
-            &is_ok,
-            &mut combo_consumer,
+            is_ok,
         );
+        while let Some(combo) = combos.next_combination() {
+            combo_consumer(combo);
+        }
     } else {
         find_combos(
+            kinds,
             board,
             inverse_placed,
             piece_set,
@@ -159,18 +579,20 @@ fn find_combos_mt<'s>(
             height,
             |placement, board, inverse_placed, piece_set| {
                 placements.push(placement);
-                if has_cyclic_dependency(inverse_placed, &placements, height) {
+                if has_cyclic_dependency(kinds, inverse_placed, &placements, height) {
+                    // no order of these placements can be placed
                 } else if board == BitBoard::filled(height) {
                     combo_consumer(&placements);
-                } else if !vertical_parity_ok(board, piece_set, height) {
-                } else if !is_ok(&placements, board, inverse_placed, height, piece_set) {
-                } else {
+                } else if kinds.can_fill(board, &piece_set, height)
+                    && is_ok.keep(&placements, board, inverse_placed, height, &piece_set)
+                {
                     let p = placements.clone();
                     let c = combo_consumer.clone();
                     let is_ok = is_ok.clone();
                     scope.spawn(move |scope| {
                         find_combos_mt(
                             scope,
+                            kinds,
                             p,
                             board,
                             inverse_placed,
@@ -190,63 +612,23 @@ fn find_combos_mt<'s>(
 }
 
 #[inline(always)]
-fn find_combos(
-    board: BitBoard,
-    inverse_placed: BitBoard,
-    piece_set: PieceSet,
+fn find_combos<const W: usize, K: PieceKinds<W>>(
+    kinds: K,
+    board: BitBoard<W>,
+    inverse_placed: BitBoard<W>,
+    piece_set: K::Set,
     abort: &AtomicBool,
     height: usize,
-    mut next: impl FnMut(Placement, BitBoard, BitBoard, PieceSet),
+    mut next: impl FnMut(K::Placement, BitBoard<W>, BitBoard<W>, K::Set),
 ) {
-    let x = board.leftmost_empty_column(height);
-    let mut y = 0;
-    for i in 0..height {
-        if !board.cell_filled(x, i) {
-            y = i;
-            break;
-        }
-    }
-    let y = y;
-
-    for &piece in &PIECES {
-        if !piece_set.contains(piece) {
-            // this piece can't be used again
-            continue;
-        }
-        for &piece_state in
-            crate::data::PIECE_STATES_BY_HEIGHT_KIND_CELLY[height - 1][piece as usize][y]
-        {
-            if x + piece_state.width() as usize > 10 {
-                // piece doesn't fit. array is sorted by width, so all future states fail this too.
-                break;
-            }
-
-            let placement = Placement {
-                kind: piece_state,
-                x: x as u8,
-            };
-            let piece_board = placement.board();
-
-            if piece_board.overlaps(board) {
-                // can't place piece here
-                continue;
-            }
-
-            // Check if we should abort the search
-            if abort.load(Ordering::Acquire) {
-                return;
-            }
-
-            let new_board = piece_board.combine(board);
-            let new_inverse_placed = inverse_placed.remove(piece_board);
-
-            next(
-                placement,
-                new_board,
-                new_inverse_placed,
-                piece_set.without(piece),
-            );
+    let mut frame = ComboFrame::<W, K>::new(board, inverse_placed, piece_set, height);
+    while let Some(placement) = frame.next_placement(kinds, height) {
+        // Check if we should abort the search
+        if abort.load(Ordering::Acquire) {
+            return;
         }
+        let (board, inverse_placed, piece_set) = frame.place(kinds, placement);
+        next(placement, board, inverse_placed, piece_set);
     }
 }
 
@@ -254,9 +636,10 @@ fn find_combos(
 /// row 2. To place the O, the S must be used to clear a line first. To place the S, the O must
 /// be used to clear a line first. Obviously, these dependencies cannot be satisfied.
 #[inline(always)]
-fn has_cyclic_dependency(
-    inverse_placed: BitBoard,
-    placements: &[Placement],
+fn has_cyclic_dependency<const W: usize, K: PieceKinds<W>>(
+    kinds: K,
+    inverse_placed: BitBoard<W>,
+    placements: &[K::Placement],
     height: usize,
 ) -> bool {
     // Initially filled spots of the field obviously provide support, but the empty parts
@@ -267,15 +650,15 @@ fn has_cyclic_dependency(
 
     // O(n^2) loop is kinda yikes, but the whole find_combinations routine is O(n!) so...
     'place: loop {
-        for &p in &*placements {
-            let piece_board = p.board();
+        for &p in placements {
+            let piece_board = kinds.board(p);
 
             if supports.overlaps(piece_board) {
                 // this basically checks if we've already placed p on the board
                 continue;
             }
 
-            if p.supported_without_clears(supports) {
+            if kinds.supported_without_clears(p, supports) {
                 // supported placement
                 supports = supports.combine(piece_board);
                 continue 'place;
@@ -295,8 +678,13 @@ fn has_cyclic_dependency(
 /// There are 4 pieces that can change vertical parity: L and J in any orientation change it by
 /// 1, vertical T changes it by 1, and vertical I changes it by 2.
 #[inline(always)]
-fn vertical_parity_ok(board: BitBoard, remaining: PieceSet, height: usize) -> bool {
-    let remaining_pieces = BitBoard::filled(height).remove(board).0.count_ones() / 4;
+fn vertical_parity_ok<const W: usize>(
+    board: BitBoard<W>,
+    remaining: PieceSet,
+    height: usize,
+) -> bool {
+    let remaining_pieces =
+        BitBoard::filled(height).remove(board).0.count_ones() / Tetrominoes::CELLS as u32;
 
     // pieces that can be placed without changing vertical parity
     let available_non_lj = remaining.0[Piece::S as usize] as u32
@@ -305,11 +693,19 @@ fn vertical_parity_ok(board: BitBoard, remaining: PieceSet, height: usize) -> bo
         + remaining.0[Piece::I as usize] as u32
         + remaining.0[Piece::O as usize] as u32;
 
-    let even_columns = 0b0101010101_0101010101_0101010101_0101010101_0101010101_0101010101;
-    let vertical_parity = (board.0 & even_columns)
-        .count_ones()
-        .abs_diff((board.0 & !even_columns).count_ones())
-        / 2;
+    let row = BitBoard::<W>::ROW;
+    let even_row = if W % 2 == 0 {
+        row / 0b11
+    } else {
+        (row << 1 | 1) / 0b11
+    };
+    // a bit at the start of each row, multiplied by the even columns of a row
+    let even_columns = BitBoard::<W>::filled(BitBoard::<W>::HEIGHT).0 / row * even_row;
+    let imbalance =
+        |b: u128| (b & even_columns).count_ones() as i32 - (b & !even_columns).count_ones() as i32;
+    // with an odd width, the filled lines have more cells in even columns than in odd columns
+    let vertical_parity =
+        imbalance(board.0).abs_diff(imbalance(BitBoard::<W>::filled(height).0)) / 2;
 
     // remaining potential for vertical parity to be changed
     let can_change = remaining.0[Piece::L as usize] as u32
@@ -324,9 +720,86 @@ fn vertical_parity_ok(board: BitBoard, remaining: PieceSet, height: usize) -> bo
 
     // number of vertical parity changes that must happen due to forced L/J pieces
     let must_change = remaining_pieces - available_non_lj.min(remaining_pieces);
-    if can_change == must_change && (vertical_parity ^ must_change) & 1 != 0 {
-        false
-    } else {
-        true
+    can_change != must_change || (vertical_parity ^ must_change) & 1 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::Mutex;
+
+    fn pco() -> BitBoard {
+        "
+        XXXX____XX
+        XXXX___XXX
+        XXXX__XXXX
+        XXXX___XXX
+        "
+        .parse()
+        .unwrap()
+    }
+
+    fn pieces() -> PieceSet {
+        "IOTSZLJ".parse().unwrap()
+    }
+
+    /// Combinations compared without regard to the order their placements were found in.
+    fn combination(combo: &[Placement]) -> Vec<u128> {
+        let mut boards: Vec<_> = combo.iter().map(|p| p.board::<10>().0).collect();
+        boards.sort_unstable();
+        boards
+    }
+
+    fn no_o(combo: &[Placement], _: BitBoard, _: BitBoard, _: usize, _: PieceSet) -> bool {
+        combo.iter().all(|p| p.kind.piece() != Piece::O)
+    }
+
+    #[test]
+    fn iterator_matches_the_callbacks() {
+        let abort = AtomicBool::new(false);
+        let mut expected = vec![];
+        find_combinations(pieces(), pco(), &abort, 4, |combo| {
+            expected.push(combo.to_vec())
+        });
+        assert!(!expected.is_empty());
+        let combos: Vec<_> = CombinationIter::new(pieces(), pco(), &abort, 4).collect();
+        assert_eq!(combos, expected);
+
+        let mt = Mutex::new(HashSet::new());
+        find_combinations_mt(pieces(), pco(), &abort, 4, |combo| {
+            mt.lock().unwrap().insert(combination(combo));
+        });
+        let all: HashSet<_> = combos.iter().map(|combo| combination(combo)).collect();
+        assert_eq!(all.len(), combos.len());
+        assert_eq!(mt.into_inner().unwrap(), all);
+    }
+
+    #[test]
+    fn pruning_matches_the_callbacks() {
+        let abort = AtomicBool::new(false);
+        let mut expected = vec![];
+        find_combinations_with_pruning(pieces(), pco(), &abort, 4, no_o, |combo| {
+            expected.push(combo.to_vec())
+        });
+        let combos: Vec<_> =
+            CombinationIter::with_pruning(pieces(), pco(), &abort, 4, no_o).collect();
+        assert_eq!(combos, expected);
+
+        let mt = Mutex::new(HashSet::new());
+        find_combinations_with_pruning_mt(pieces(), pco(), &abort, 4, no_o, |combo| {
+            mt.lock().unwrap().insert(combination(combo));
+        });
+        let pruned: HashSet<_> = combos.iter().map(|combo| combination(combo)).collect();
+        assert_eq!(mt.into_inner().unwrap(), pruned);
+
+        let all: Vec<_> = CombinationIter::new(pieces(), pco(), &abort, 4)
+            // the placement that completes a combination isn't pruned
+            .filter(|combo| no_o(&combo[..combo.len() - 1], pco(), pco(), 4, pieces()))
+            .map(|combo| combination(&combo))
+            .collect();
+        assert!(!all.is_empty());
+        assert!(all.len() < CombinationIter::new(pieces(), pco(), &abort, 4).count());
+        assert_eq!(all.into_iter().collect::<HashSet<_>>(), pruned);
     }
 }
@@ -0,0 +1,205 @@
+use crate::combination::Tetrominoes;
+use crate::solve::{solve_placement_combo, PieceSequence};
+use crate::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Which queues can build each of a list of setups, as returned by `cover`.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Coverage {
+    pub queues: Vec<Vec<Piece>>,
+    /// `covered[setup][queue]` is true if the queue can build the setup.
+    pub covered: Vec<Vec<bool>>,
+}
+
+impl Coverage {
+    /// How many of the queues can build the setup.
+    pub fn setup_rate(&self, setup: usize) -> Rate {
+        self.group_rate(&[setup])
+    }
+
+    /// How many of the queues can build at least one setup of the group.
+    pub fn group_rate(&self, group: &[usize]) -> Rate {
+        Rate {
+            successes: (0..self.queues.len())
+                .filter(|&q| group.iter().any(|&s| self.covered[s][q]))
+                .count(),
+            total: self.queues.len(),
+        }
+    }
+
+    /// Queues that can't build any of the setups.
+    pub fn uncovered(&self) -> Vec<&[Piece]> {
+        let all: Vec<_> = (0..self.covered.len()).collect();
+        (0..self.queues.len())
+            .filter(|&q| !all.iter().any(|&s| self.covered[s][q]))
+            .map(|q| &*self.queues[q])
+            .collect()
+    }
+
+    /// The group of `size` setups that covers the most queues, and how many it covers. Every
+    /// group is tried, so this is only practical for small numbers of setups.
+    pub fn best_group(&self, size: usize) -> (Vec<usize>, Rate) {
+        let mut best = (vec![], self.group_rate(&[]));
+        let mut group = vec![];
+        self.best_group_rec(size.min(self.covered.len()), 0, &mut group, &mut best);
+        best
+    }
+
+    /// The smallest group of setups that covers every queue that any of the setups covers.
+    pub fn smallest_group(&self) -> (Vec<usize>, Rate) {
+        let all: Vec<_> = (0..self.covered.len()).collect();
+        let most = self.group_rate(&all).successes;
+        for size in 0..self.covered.len() {
+            let (group, rate) = self.best_group(size);
+            if rate.successes == most {
+                return (group, rate);
+            }
+        }
+        let rate = self.group_rate(&all);
+        (all, rate)
+    }
+
+    fn best_group_rec(
+        &self,
+        size: usize,
+        start: usize,
+        group: &mut Vec<usize>,
+        best: &mut (Vec<usize>, Rate),
+    ) {
+        if group.len() == size {
+            let rate = self.group_rate(group);
+            if best.0.len() != size || rate.successes > best.1.successes {
+                *best = (group.clone(), rate);
+            }
+            return;
+        }
+        for s in start..self.covered.len() {
+            group.push(s);
+            self.best_group_rec(size, s + 1, group, best);
+            group.pop();
+        }
+    }
+}
+
+/// Determines which of `queues` can build each setup on the board, where a setup is a set of
+/// placements that can be made in any order the queue and hold allow.
+///
+/// Queues that only differ in pieces that can't be used are only checked once per setup. If
+/// `abort` is set, queues that have not been checked yet are counted as not covering.
+pub fn cover<const W: usize>(
+    board: BitBoard<W>,
+    setups: &[impl AsRef<[Placement]>],
+    queues: impl IntoIterator<Item = impl AsRef<[Piece]>>,
+    hold_allowed: bool,
+    abort: &AtomicBool,
+    placeability_judge: impl Fn(BitBoard<W>, Placement) -> bool,
+) -> Coverage {
+    let queues: Vec<Vec<Piece>> = queues.into_iter().map(|q| q.as_ref().to_vec()).collect();
+    let mut covered = vec![];
+    for setup in setups {
+        let setup = setup.as_ref();
+        let mut cache = HashMap::new();
+        let mut by_queue = vec![];
+        for queue in &queues {
+            if queue.len() < setup.len() || abort.load(Ordering::Acquire) {
+                by_queue.push(false);
+                continue;
+            }
+            let sequence: PieceSequence = queue
+                .iter()
+                .copied()
+                .take(setup.len() + hold_allowed as usize)
+                .collect();
+            by_queue.push(*cache.entry(sequence).or_insert_with(|| {
+                let mut found = false;
+                solve_placement_combo(
+                    Tetrominoes,
+                    sequence,
+                    board,
+                    setup,
+                    hold_allowed,
+                    true,
+                    &placeability_judge,
+                    |_| found = true,
+                );
+                found
+            }));
+        }
+        covered.push(by_queue);
+    }
+    Coverage { queues, covered }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Piece::*;
+
+    fn placement(piece: Piece, cells: &str) -> Placement {
+        let cells: BitBoard = cells.parse().unwrap();
+        placeability::placements(BitBoard::<10>(0), piece, placeability::always)
+            .into_iter()
+            .map(|(placement, _)| placement)
+            .find(|placement| placement.board() == cells)
+            .unwrap()
+    }
+
+    fn example(hold_allowed: bool) -> Coverage {
+        let setups = [
+            vec![placement(I, "IIII______")],
+            vec![placement(O, "OO________\nOO________")],
+            vec![placement(T, "_T________\nTTT_______")],
+        ];
+        let queues = [[I, O], [O, I], [T, S], [S, O]];
+        cover(
+            BitBoard::<10>(0),
+            &setups,
+            queues,
+            hold_allowed,
+            &AtomicBool::new(false),
+            placeability::always,
+        )
+    }
+
+    #[test]
+    fn checks_each_queue_against_each_setup() {
+        let coverage = example(true);
+        assert_eq!(
+            coverage.covered,
+            vec![
+                vec![true, true, false, false],
+                vec![true, true, false, true],
+                vec![false, false, true, false],
+            ]
+        );
+        assert_eq!(
+            coverage.setup_rate(1),
+            Rate {
+                successes: 3,
+                total: 4
+            }
+        );
+
+        let without_hold = example(false);
+        assert_eq!(
+            without_hold.covered,
+            vec![
+                vec![true, false, false, false],
+                vec![false, true, false, false],
+                vec![false, false, true, false],
+            ]
+        );
+        assert_eq!(without_hold.uncovered(), vec![&[S, O][..]]);
+    }
+
+    #[test]
+    fn picks_the_best_groups() {
+        let coverage = example(true);
+        assert_eq!(coverage.best_group(1).0, vec![1]);
+        assert_eq!(coverage.best_group(2).0, vec![1, 2]);
+        assert_eq!(coverage.best_group(2).1.successes, 4);
+        assert_eq!(coverage.smallest_group().0, vec![1, 2]);
+        assert!(coverage.uncovered().is_empty());
+    }
+}
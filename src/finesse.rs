@@ -0,0 +1,531 @@
+use crate::kicks::RotationSystem;
+use crate::placeability::{Field, Playfield};
+use crate::*;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::AtomicBool;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Input {
+    Left,
+    Right,
+    DasLeft,
+    DasRight,
+    Cw,
+    Ccw,
+    Rotate180,
+    /// Drops the piece as far as it can go without locking it.
+    SoftDrop,
+    /// Soft drops the piece by this many rows without landing it, to rotate or shift it in
+    /// midair.
+    PartialSoftDrop(u32),
+    HardDrop,
+}
+
+/// Finds a shortest sequence of inputs that places a piece at `placement`, ending with a hard
+/// drop. Returns `None` if the placement can't be reached.
+pub fn inputs<const W: usize>(
+    rotation_system: &impl RotationSystem,
+    field: &Field<W>,
+    board: BitBoard<W>,
+    placement: Placement,
+) -> Option<Vec<Input>> {
+    shortest_path(rotation_system, field, board, placement)
+        .map(|path| path.into_iter().map(|step| step.input).collect())
+}
+
+/// How long it takes to place pieces, estimated from the input sequence used for each of them.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Cost {
+    pub key_presses: u32,
+    pub frames: u32,
+    pub soft_drops: u32,
+    /// Placements where the piece is rotated after landing.
+    pub spins: u32,
+    /// Placements where the piece is shifted after landing.
+    pub tucks: u32,
+}
+
+impl std::ops::Add for Cost {
+    type Output = Cost;
+    fn add(self, other: Cost) -> Cost {
+        Cost {
+            key_presses: self.key_presses + other.key_presses,
+            frames: self.frames + other.frames,
+            soft_drops: self.soft_drops + other.soft_drops,
+            spins: self.spins + other.spins,
+            tucks: self.tucks + other.tucks,
+        }
+    }
+}
+
+/// Handling settings used to estimate the number of frames inputs take.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Handling {
+    /// Delayed auto shift: frames a direction must be held before the piece starts moving.
+    pub das: u32,
+    /// Auto repeat rate: frames between each shift after DAS is charged.
+    pub arr: u32,
+    /// Frames for the piece to fall one row while soft dropping.
+    pub soft_drop: u32,
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Handling {
+            das: 10,
+            arr: 2,
+            soft_drop: 1,
+        }
+    }
+}
+
+pub fn placement_cost<const W: usize>(
+    rotation_system: &impl RotationSystem,
+    field: &Field<W>,
+    board: BitBoard<W>,
+    placement: Placement,
+    handling: &Handling,
+) -> Option<Cost> {
+    let path = shortest_path(rotation_system, field, board, placement)?;
+
+    let mut cost = Cost {
+        key_presses: path.len() as u32,
+        ..Cost::default()
+    };
+    let mut landed = None;
+    for step in &path {
+        cost.frames += match step.input {
+            Input::DasLeft | Input::DasRight => {
+                let distance = (step.to.x - step.from.x).unsigned_abs();
+                handling.das + handling.arr * (distance - 1)
+            }
+            Input::SoftDrop | Input::PartialSoftDrop(_) => {
+                handling.soft_drop * (step.from.y - step.to.y) as u32
+            }
+            _ => 1,
+        };
+        match step.input {
+            Input::SoftDrop => {
+                cost.soft_drops += 1;
+                landed = Some(Input::SoftDrop);
+            }
+            Input::PartialSoftDrop(_) => cost.soft_drops += 1,
+            Input::HardDrop => {}
+            input => landed = landed.map(|_| input),
+        }
+    }
+    match landed {
+        Some(Input::Cw) | Some(Input::Ccw) | Some(Input::Rotate180) => cost.spins += 1,
+        Some(Input::SoftDrop) | None => {}
+        Some(_) => cost.tucks += 1,
+    }
+
+    Some(cost)
+}
+
+/// The total cost of placing each placement of a solution one after another.
+pub fn solution_cost<const W: usize>(
+    rotation_system: &impl RotationSystem,
+    field: &Field<W>,
+    mut board: BitBoard<W>,
+    solution: &[Placement],
+    handling: &Handling,
+) -> Option<Cost> {
+    let mut cost = Cost::default();
+    for &placement in solution {
+        cost = cost + placement_cost(rotation_system, field, board, placement, handling)?;
+        board = board.combine(placement.board());
+    }
+    Some(cost)
+}
+
+/// Finds every perfect clear solution and returns them sorted from cheapest to most expensive.
+/// If `limit` is given, only that many of the cheapest solutions are kept.
+#[allow(clippy::too_many_arguments)]
+pub fn solve_pc_by_cost<const W: usize>(
+    queue: &[Piece],
+    board: BitBoard<W>,
+    hold_allowed: bool,
+    abort: &AtomicBool,
+    rotation_system: impl RotationSystem + Clone,
+    field: Field<W>,
+    handling: &Handling,
+    limit: Option<usize>,
+) -> Vec<(Vec<Placement>, Cost)> {
+    let mut solutions = vec![];
+    let keep = |solutions: &mut Vec<(Vec<Placement>, Cost)>| {
+        solutions.sort_by_key(|&(_, cost)| cost);
+        if let Some(limit) = limit {
+            solutions.truncate(limit);
+        }
+    };
+    solve_pc(
+        queue,
+        board,
+        hold_allowed,
+        false,
+        abort,
+        placeability::movement_in(rotation_system.clone(), field),
+        |soln| {
+            if let Some(cost) = solution_cost(&rotation_system, &field, board, soln, handling) {
+                solutions.push((soln.to_vec(), cost));
+                if limit.is_some_and(|limit| solutions.len() >= 2 * limit.max(1)) {
+                    keep(&mut solutions);
+                }
+            }
+        },
+    );
+    keep(&mut solutions);
+    solutions
+}
+
+/// Finds the inputs for each placement of a solution, placing them one after another starting
+/// from `board`. Returns `None` if any placement can't be reached.
+pub fn solution_inputs<const W: usize>(
+    rotation_system: &impl RotationSystem,
+    field: &Field<W>,
+    mut board: BitBoard<W>,
+    solution: &[Placement],
+) -> Option<Vec<Vec<Input>>> {
+    let mut result = Vec::with_capacity(solution.len());
+    for &placement in solution {
+        result.push(inputs(rotation_system, field, board, placement)?);
+        board = board.combine(placement.board());
+    }
+    Some(result)
+}
+
+struct Step {
+    input: Input,
+    from: SrsPiece,
+    to: SrsPiece,
+}
+
+/// Stopping a piece in midair takes precise timing, so partial soft drops are only used for
+/// placements that can't be reached without them.
+fn shortest_path<const W: usize>(
+    rotation_system: &impl RotationSystem,
+    field: &Field<W>,
+    board: BitBoard<W>,
+    placement: Placement,
+) -> Option<Vec<Step>> {
+    path(rotation_system, field, board, placement, false)
+        .or_else(|| path(rotation_system, field, board, placement, true))
+}
+
+fn path<const W: usize>(
+    rotation_system: &impl RotationSystem,
+    field: &Field<W>,
+    board: BitBoard<W>,
+    placement: Placement,
+    midair: bool,
+) -> Option<Vec<Step>> {
+    let playfield = Playfield::new(board, field);
+    let targets = placement.srs_piece(board);
+
+    let spawn = playfield.spawn(rotation_system, placement.kind.piece());
+    if !playfield.fits(spawn) {
+        return None;
+    }
+
+    let mut previous = HashMap::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(spawn);
+
+    while let Some(piece) = queue.pop_front() {
+        let dropped = playfield.dropped(piece);
+        if targets.contains(&dropped) {
+            let mut path = vec![Step {
+                input: Input::HardDrop,
+                from: piece,
+                to: dropped,
+            }];
+            let mut to = piece;
+            while let Some(&(from, input)) = previous.get(&to) {
+                path.push(Step { input, from, to });
+                to = from;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        let mut moves = vec![
+            (Input::Left, playfield.shifted(piece, -1, 0)),
+            (Input::Right, playfield.shifted(piece, 1, 0)),
+            (Input::DasLeft, das(&playfield, piece, -1)),
+            (Input::DasRight, das(&playfield, piece, 1)),
+            (
+                Input::Cw,
+                playfield.rotated(rotation_system, piece, piece.rotation.cw()),
+            ),
+            (
+                Input::Ccw,
+                playfield.rotated(rotation_system, piece, piece.rotation.ccw()),
+            ),
+            (
+                Input::Rotate180,
+                if rotation_system.has_180() {
+                    playfield.rotated(rotation_system, piece, piece.rotation.opposite())
+                } else {
+                    None
+                },
+            ),
+            (Input::SoftDrop, Some(dropped)),
+        ];
+        if midair {
+            // stopping at every height on the way down
+            moves.extend((1..piece.y - dropped.y).map(|rows| {
+                let lower = SrsPiece {
+                    y: piece.y - rows,
+                    ..piece
+                };
+                (Input::PartialSoftDrop(rows as u32), Some(lower))
+            }));
+        }
+        for &(input, next) in &moves {
+            if let Some(next) = next {
+                if next == spawn || previous.contains_key(&next) {
+                    continue;
+                }
+                previous.insert(next, (piece, input));
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+fn das<const W: usize>(playfield: &Playfield<W>, mut piece: SrsPiece, dx: i32) -> Option<SrsPiece> {
+    let mut moved = false;
+    while let Some(next) = playfield.shifted(piece, dx, 0) {
+        piece = next;
+        moved = true;
+    }
+    if moved {
+        Some(piece)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kicks::Srs;
+
+    fn placement(board: BitBoard, piece: Piece, cells: &str) -> Placement {
+        let cells: BitBoard = cells.parse().unwrap();
+        placeability::placements(board, piece, placeability::always)
+            .into_iter()
+            .map(|(placement, _)| placement)
+            .find(|placement| placement.board() == cells)
+            .unwrap()
+    }
+
+    #[test]
+    fn drops_and_shifts_from_spawn() {
+        let field = Field::default();
+        let board: BitBoard = BitBoard(0);
+        let spawn = placement(board, Piece::O, "____XX____\n____XX____");
+        assert_eq!(
+            inputs(&Srs, &field, board, spawn),
+            Some(vec![Input::HardDrop])
+        );
+        let left = placement(board, Piece::T, "_X________\nXXX_______");
+        assert_eq!(
+            inputs(&Srs, &field, board, left),
+            Some(vec![Input::DasLeft, Input::HardDrop])
+        );
+        let right = placement(board, Piece::I, "______XXXX");
+        assert_eq!(
+            inputs(&Srs, &field, board, right),
+            Some(vec![Input::DasRight, Input::HardDrop])
+        );
+        // no 180 rotations in SRS
+        let flipped = placement(board, Piece::T, "___XXX____\n____X_____");
+        assert_eq!(
+            inputs(&Srs, &field, board, flipped),
+            Some(vec![Input::Cw, Input::Cw, Input::HardDrop])
+        );
+    }
+
+    #[test]
+    fn tucks_and_spins() {
+        let field = Field::default();
+        let board: BitBoard = "
+            ________XX
+            __________
+            XXXXX_____
+        "
+        .parse()
+        .unwrap();
+        let tuck = placement(board, Piece::S, "__________\n_______XX_\n______XX__");
+        assert_eq!(
+            inputs(&Srs, &field, board, tuck),
+            Some(vec![
+                Input::Right,
+                Input::Right,
+                Input::SoftDrop,
+                Input::Right,
+                Input::HardDrop
+            ])
+        );
+
+        let board: BitBoard = "
+            XXX_______
+            XX___XXXXX
+            XXX_XXXXXX
+        "
+        .parse()
+        .unwrap();
+        let tsd = placement(board, Piece::T, "__________\n__XXX_____\n___X______");
+        assert_eq!(
+            inputs(&Srs, &field, board, tsd),
+            Some(vec![
+                Input::Left,
+                Input::Cw,
+                Input::SoftDrop,
+                Input::Cw,
+                Input::HardDrop
+            ])
+        );
+    }
+
+    #[test]
+    fn costs_count_inputs_and_frames() {
+        let field = Field::default();
+        let handling = Handling::default();
+        let board: BitBoard = BitBoard(0);
+        let spawn = placement(board, Piece::O, "____XX____\n____XX____");
+        let left = placement(board, Piece::T, "_X________\nXXX_______");
+        let spawn = placement_cost(&Srs, &field, board, spawn, &handling).unwrap();
+        let left = placement_cost(&Srs, &field, board, left, &handling).unwrap();
+        assert_eq!(
+            spawn,
+            Cost {
+                key_presses: 1,
+                frames: 1,
+                ..Cost::default()
+            }
+        );
+        // DAS, then 2 more columns at the auto repeat rate, then the hard drop
+        assert_eq!(
+            left,
+            Cost {
+                key_presses: 2,
+                frames: 10 + 2 * 2 + 1,
+                ..Cost::default()
+            }
+        );
+
+        let board: BitBoard = "
+            ________XX
+            __________
+            XXXXX_____
+        "
+        .parse()
+        .unwrap();
+        let tuck = placement(board, Piece::S, "__________\n_______XX_\n______XX__");
+        let tuck = placement_cost(&Srs, &field, board, tuck, &handling).unwrap();
+        assert_eq!(
+            tuck,
+            Cost {
+                key_presses: 5,
+                frames: 4 + 20,
+                soft_drops: 1,
+                tucks: 1,
+                ..Cost::default()
+            }
+        );
+
+        let board: BitBoard = "
+            XXX_______
+            XX___XXXXX
+            XXX_XXXXXX
+        "
+        .parse()
+        .unwrap();
+        let tsd = placement(board, Piece::T, "__________\n__XXX_____\n___X______");
+        let tsd = placement_cost(&Srs, &field, board, tsd, &handling).unwrap();
+        assert_eq!(
+            tsd,
+            Cost {
+                key_presses: 5,
+                frames: 4 + 19,
+                soft_drops: 1,
+                spins: 1,
+                ..Cost::default()
+            }
+        );
+
+        assert!(spawn < left && left < tsd && tsd < tuck);
+    }
+
+    #[test]
+    fn ranks_solutions_by_cost() {
+        let board: BitBoard = "
+            XXXX______
+            XXXX______
+        "
+        .parse()
+        .unwrap();
+        let queue = [Piece::O, Piece::I, Piece::I, Piece::T];
+        let field = Field::default();
+        let handling = Handling::default();
+        let abort = AtomicBool::new(false);
+        let ranked = solve_pc_by_cost(&queue, board, true, &abort, Srs, field, &handling, None);
+        assert!(ranked.len() > 2);
+        for (solution, cost) in &ranked {
+            assert_eq!(
+                solution_cost(&Srs, &field, board, solution, &handling),
+                Some(*cost)
+            );
+        }
+        assert!(ranked.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+
+        let cheapest =
+            solve_pc_by_cost(&queue, board, true, &abort, Srs, field, &handling, Some(2));
+        assert_eq!(cheapest.len(), 2);
+        assert_eq!(cheapest[0].1, ranked[0].1);
+        assert_eq!(cheapest[1].1, ranked[1].1);
+    }
+
+    #[test]
+    fn stops_in_midair_when_it_has_to() {
+        let field = Field::default();
+        // the L has to be shifted into the gap halfway down the well
+        let board: BitBoard = "
+            XXXXXXXX__
+            XXXXXXXX__
+            XXXX______
+            XXXXXXXX__
+            XXXXXXXX__
+            XXXXXXXX__
+        "
+        .parse()
+        .unwrap();
+        let l = placement(
+            board,
+            Piece::L,
+            "
+            __________
+            __________
+            _______XX_
+            ________X_
+            ________X_
+            __________
+            ",
+        );
+        assert_eq!(
+            inputs(&Srs, &field, board, l),
+            Some(vec![
+                Input::Ccw,
+                Input::DasRight,
+                Input::PartialSoftDrop(18),
+                Input::Left,
+                Input::HardDrop
+            ])
+        );
+    }
+}
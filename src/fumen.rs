@@ -0,0 +1,231 @@
+//! Conversion between boards, placements and [fumen](http://fumen.zui.jp) diagrams.
+
+use crate::*;
+
+pub use ::fumen::{CellColor, Fumen, Page};
+
+/// Decodes the first page of a fumen into a board and queue.
+///
+/// Any data before `v115@` is ignored, so full fumen URLs can be passed directly. The queue is
+/// read from the page comment; see `page_queue`. Returns `None` if the data is invalid or the
+/// field has filled cells above the `MAX_HEIGHT`th row.
+pub fn decode(data: &str) -> Option<(BitBoard, Vec<Piece>)> {
+    let fumen = Fumen::decode(&data[data.find("v115@")?..]).ok()?;
+    let page = fumen.pages.first()?;
+    Some((page_board(page)?, page_queue(page)))
+}
+
+/// Returns the board in the field of a page, or `None` if it has filled cells above the
+/// `MAX_HEIGHT`th row.
+pub fn page_board(page: &Page) -> Option<BitBoard> {
+    let mut board = 0;
+    for (y, row) in page.field.iter().enumerate() {
+        for (x, &cell) in row.iter().enumerate() {
+            if cell != CellColor::Empty {
+                if y >= MAX_HEIGHT {
+                    return None;
+                }
+                board |= 1 << (x + 10 * y);
+            }
+        }
+    }
+    Some(BitBoard(board))
+}
+
+/// Reads a queue from a page comment, either as plain piece letters (`TIOSZLJ`) or in the
+/// format used by solution-finder (`#Q=[L](T)IOSZJ`).
+///
+/// The hold piece and current piece are returned as the first pieces in the queue, which is
+/// equivalent when hold is allowed.
+pub fn page_queue(page: &Page) -> Vec<Piece> {
+    let comment = page.comment.as_deref().unwrap_or("");
+    let comment = comment.strip_prefix("#Q=").unwrap_or(comment);
+    comment
+        .chars()
+        .take_while(|c| !c.is_whitespace())
+        .filter(|c| !"[]()".contains(*c))
+        .map_while(|c| {
+            Some(match c {
+                'I' => Piece::I,
+                'O' => Piece::O,
+                'T' => Piece::T,
+                'L' => Piece::L,
+                'J' => Piece::J,
+                'S' => Piece::S,
+                'Z' => Piece::Z,
+                _ => return None,
+            })
+        })
+        .collect()
+}
+
+/// Creates a fumen showing a solution with one page per placement.
+pub fn solution_fumen(board: BitBoard, solution: &[Placement]) -> Fumen {
+    let mut fumen = Fumen::default();
+    add_solution_pages(&mut fumen, board, solution);
+    fumen
+}
+
+/// Appends pages showing a solution to a fumen, one page per placement.
+///
+/// The first page shows `board` in grey; later pages are created by fumen's line clear rules.
+pub fn add_solution_pages(fumen: &mut Fumen, board: BitBoard, solution: &[Placement]) {
+    let mut on = board;
+    let pieces: Vec<_> = solution
+        .iter()
+        .map(|placement| {
+            let srs = placement.srs_piece(on).into_iter().next().unwrap();
+            on = on.combine(placement.board());
+            srs
+        })
+        .collect();
+    add_srs_piece_pages(fumen, board, &pieces);
+}
+
+/// Appends pages to a fumen, one page per piece.
+///
+/// The first page shows `board` in grey. Each piece is positioned relative to the field after
+/// the previous pieces have locked and any filled lines have been cleared.
+pub fn add_srs_piece_pages(fumen: &mut Fumen, board: BitBoard, pieces: &[SrsPiece]) {
+    for (i, &piece) in pieces.iter().enumerate() {
+        let page = if i == 0 {
+            let mut page = Page::default();
+            blit(&mut page, board, CellColor::Grey);
+            fumen.pages.push(page);
+            fumen.pages.last_mut().unwrap()
+        } else {
+            fumen.add_page()
+        };
+        page.piece = Some(fumen_piece(piece));
+    }
+}
+
+/// Creates a single page showing a combination, with each piece drawn in its own color.
+pub fn combination_page(board: BitBoard, combination: &[Placement]) -> Page {
+    let mut page = Page::default();
+    blit(&mut page, board, CellColor::Grey);
+    draw_placements(&mut page, combination);
+    page
+}
+
+/// Draws placements onto the field of a page, each in the color of its piece.
+pub fn draw_placements(page: &mut Page, placements: &[Placement]) {
+    for placement in placements {
+        blit(
+            page,
+            placement.board(),
+            fumen_piece_type(placement.kind.piece()).into(),
+        );
+    }
+}
+
+/// Fills the cells of a page's field that are filled on a board.
+pub fn blit(page: &mut Page, board: BitBoard, color: CellColor) {
+    for y in 0..MAX_HEIGHT {
+        for x in 0..10 {
+            if board.cell_filled(x, y) {
+                page.field[y][x] = color;
+            }
+        }
+    }
+}
+
+pub fn fumen_piece(piece: SrsPiece) -> ::fumen::Piece {
+    ::fumen::Piece {
+        kind: fumen_piece_type(piece.piece),
+        rotation: fumen_rotation(piece.rotation),
+        x: piece.x as u32,
+        y: piece.y as u32,
+    }
+}
+
+pub fn fumen_piece_type(piece: Piece) -> ::fumen::PieceType {
+    match piece {
+        Piece::I => ::fumen::PieceType::I,
+        Piece::T => ::fumen::PieceType::T,
+        Piece::O => ::fumen::PieceType::O,
+        Piece::S => ::fumen::PieceType::S,
+        Piece::Z => ::fumen::PieceType::Z,
+        Piece::L => ::fumen::PieceType::L,
+        Piece::J => ::fumen::PieceType::J,
+    }
+}
+
+pub fn fumen_rotation(rotation: Rotation) -> ::fumen::RotationState {
+    match rotation {
+        Rotation::North => ::fumen::RotationState::North,
+        Rotation::South => ::fumen::RotationState::South,
+        Rotation::West => ::fumen::RotationState::West,
+        Rotation::East => ::fumen::RotationState::East,
+    }
+}
+
+#[cfg(all(test, feature = "fumen"))]
+mod tests {
+    use super::*;
+
+    fn pco() -> BitBoard {
+        "
+        XX____XXXX
+        XXX___XXXX
+        XXXX__XXXX
+        "
+        .parse()
+        .unwrap()
+    }
+
+    #[test]
+    fn round_trips_boards_and_queues() {
+        let mut page = Page::default();
+        blit(&mut page, pco(), CellColor::Grey);
+        page.comment = Some("ITSZ".to_string());
+        let fumen = Fumen {
+            pages: vec![page],
+            ..Fumen::default()
+        };
+        assert_eq!(
+            decode(&fumen.encode()),
+            Some((pco(), vec![Piece::I, Piece::T, Piece::S, Piece::Z]))
+        );
+    }
+
+    #[test]
+    fn decodes_solution_finder_queues() {
+        let url = "https://harddrop.com/fumen/?v115@HhB8DeG8CeH8BeD8JeAgWaAFLDmClcJSAVzbSAVG88AYe88A5tytC6/AAA";
+        use Piece::*;
+        assert_eq!(decode(url), Some((pco(), vec![L, T, I, O, S, Z, J])));
+        assert_eq!(decode("not a fumen"), None);
+    }
+
+    #[test]
+    fn rejects_cells_above_the_board() {
+        let mut page = Page::default();
+        page.field[MAX_HEIGHT][0] = CellColor::Grey;
+        assert_eq!(page_board(&page), None);
+    }
+
+    #[test]
+    fn shows_one_page_per_placement() {
+        let board: BitBoard = "
+            XXXX______
+            XXXX______
+        "
+        .parse()
+        .unwrap();
+        let mut solution = None;
+        crate::solve_pc(
+            &[Piece::O, Piece::I, Piece::I],
+            board,
+            true,
+            false,
+            &std::sync::atomic::AtomicBool::new(false),
+            placeability::always,
+            |soln| solution = Some(soln.to_vec()),
+        );
+        let fumen = solution_fumen(board, &solution.unwrap());
+
+        assert_eq!(fumen.pages.len(), 3);
+        assert_eq!(page_board(&fumen.pages[0]), Some(board));
+        assert!(fumen.pages.iter().all(|page| page.piece.is_some()));
+    }
+}
@@ -0,0 +1,259 @@
+//! Solving for line clears that don't have to be perfect clears, such as half PCs that leave
+//! some cells behind.
+
+use crate::combination::Tetrominoes;
+use crate::*;
+use std::collections::HashSet;
+use std::sync::atomic::AtomicBool;
+
+/// A line clear objective: clear exactly `lines` lines and leave the given cells behind.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct LineClearGoal<const W: usize = 10> {
+    pub lines: usize,
+    pub leftover: Leftover<W>,
+}
+
+/// What must be left on the board once the lines are cleared.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Leftover<const W: usize = 10> {
+    /// Exactly these cells, counted from the bottom of the board after the lines are cleared.
+    /// None of its rows may be filled.
+    Exactly(BitBoard<W>),
+    /// Any cells within this many rows from the bottom.
+    AtMost(usize),
+}
+
+/// Finds the ways to reach the goal from the board using pieces from the queue, with each
+/// solution given in placement order.
+///
+/// With `Leftover::Exactly`, every choice of which rows end up cleared is tried, and the cells
+/// to fill are found with the combination finder like `find_setups` does, so this is about as
+/// fast as `solve_pc`. With `Leftover::AtMost`, the cells left over aren't known up front, so
+/// placements are searched one at a time like `find_setups_by` does, using the fewest pieces
+/// that reach the goal. That is much slower for large numbers of pieces.
+///
+/// If `unique` is set, each combination of placements is only given in one order.
+#[allow(clippy::too_many_arguments)]
+pub fn solve_line_clears<const W: usize>(
+    queue: &[Piece],
+    board: BitBoard<W>,
+    goal: LineClearGoal<W>,
+    hold_allowed: bool,
+    unique: bool,
+    abort: &AtomicBool,
+    placeability_judge: impl Fn(BitBoard<W>, Placement) -> bool,
+    mut solution_consumer: impl FnMut(&[Placement]),
+) {
+    match goal.leftover {
+        Leftover::Exactly(leftover) => {
+            let rows = (0..BitBoard::<W>::HEIGHT)
+                .filter(|&y| leftover.0 >> (W * y) & BitBoard::<W>::ROW != 0)
+                .map(|y| y + 1)
+                .max()
+                .unwrap_or(0);
+            let height = goal.lines + rows;
+            if height > BitBoard::<W>::HEIGHT || (0..rows).any(|y| leftover.line_filled(y)) {
+                return;
+            }
+            for_each_cleared_rows(height, goal.lines, &mut vec![], &mut |cleared| {
+                let target = target_board(leftover, cleared, height);
+                if board.remove(target) == BitBoard(0) {
+                    find_setups(
+                        queue,
+                        board,
+                        target,
+                        height,
+                        hold_allowed,
+                        unique,
+                        abort,
+                        &placeability_judge,
+                        &mut solution_consumer,
+                    );
+                }
+            });
+        }
+        Leftover::AtMost(rows) => {
+            let height = goal.lines + rows;
+            if height > BitBoard::<W>::HEIGHT {
+                return;
+            }
+            let is_ok = |b: BitBoard<W>| {
+                (0..height).filter(|&y| b.line_filled(y)).count() == goal.lines
+                    && b.lines_cleared().remove(BitBoard::filled(rows)) == BitBoard(0)
+            };
+            let filled = board.0.count_ones() as usize;
+            let fewest = (W * goal.lines)
+                .saturating_sub(filled)
+                .div_ceil(Tetrominoes::CELLS);
+            // the cells of each placement, sorted, for telling apart combinations of placements
+            let mut combinations = HashSet::new();
+            for pieces in fewest..=queue.len() {
+                if filled + Tetrominoes::CELLS * pieces > W * height {
+                    break;
+                }
+                let mut found = false;
+                find_setups_by(
+                    queue,
+                    board,
+                    pieces,
+                    height,
+                    hold_allowed,
+                    abort,
+                    &placeability_judge,
+                    is_ok,
+                    |soln| {
+                        found = true;
+                        if unique {
+                            let mut cells: Vec<_> = soln.iter().map(|p| p.board::<W>().0).collect();
+                            cells.sort_unstable();
+                            if !combinations.insert(cells) {
+                                return;
+                            }
+                        }
+                        solution_consumer(soln)
+                    },
+                );
+                if found {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Calls `f` with every set of `lines` rows out of the lowest `height`, lowest first.
+fn for_each_cleared_rows(
+    height: usize,
+    lines: usize,
+    cleared: &mut Vec<usize>,
+    f: &mut impl FnMut(&[usize]),
+) {
+    if cleared.len() == lines {
+        f(cleared);
+        return;
+    }
+    let lowest = cleared.last().map_or(0, |&y| y + 1);
+    for y in lowest..=height - (lines - cleared.len()) {
+        cleared.push(y);
+        for_each_cleared_rows(height, lines, cleared, f);
+        cleared.pop();
+    }
+}
+
+/// The board before the lines are cleared: the cleared rows filled, and the rows of `leftover`
+/// in between.
+fn target_board<const W: usize>(
+    leftover: BitBoard<W>,
+    cleared: &[usize],
+    height: usize,
+) -> BitBoard<W> {
+    let mut target = 0;
+    let mut row = 0;
+    for y in 0..height {
+        if cleared.contains(&y) {
+            target |= BitBoard::<W>::ROW << (W * y);
+        } else {
+            target |= (leftover.0 >> (W * row) & BitBoard::<W>::ROW) << (W * y);
+            row += 1;
+        }
+    }
+    BitBoard(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Piece::*;
+
+    fn solutions(
+        queue: &[Piece],
+        board: BitBoard,
+        goal: LineClearGoal,
+        unique: bool,
+    ) -> Vec<Vec<Placement>> {
+        let mut solutions = vec![];
+        solve_line_clears(
+            queue,
+            board,
+            goal,
+            true,
+            unique,
+            &AtomicBool::new(false),
+            placeability::hard_drop_only,
+            |soln| solutions.push(soln.to_vec()),
+        );
+        solutions
+    }
+
+    fn after(board: BitBoard, solution: &[Placement]) -> BitBoard {
+        solution
+            .iter()
+            .fold(board, |board, p| board.combine(p.board()))
+    }
+
+    #[test]
+    fn exact_leftover() {
+        // the O sticks out of the line the two I pieces clear
+        let goal = LineClearGoal {
+            lines: 1,
+            leftover: Leftover::Exactly("XX________".parse().unwrap()),
+        };
+        let all = solutions(&[I, I, O], BitBoard(0), goal, false);
+        // either I can go first, and the O can come first or second through hold
+        assert_eq!(all.len(), 4);
+        for solution in &all {
+            assert_eq!(
+                after(BitBoard(0), solution).lines_cleared(),
+                "XX________".parse().unwrap()
+            );
+        }
+        assert_eq!(solutions(&[I, I, O], BitBoard(0), goal, true).len(), 1);
+        assert!(solutions(&[I, I, T], BitBoard(0), goal, false).is_empty());
+
+        // a leftover with a full row can't be left behind
+        let goal = LineClearGoal {
+            lines: 1,
+            leftover: Leftover::Exactly(BitBoard::filled(1)),
+        };
+        assert!(solutions(&[I, I, O, I, I, O], BitBoard(0), goal, false).is_empty());
+    }
+
+    #[test]
+    fn half_pc() {
+        // clear the bottom two lines of a 4 line PC setup, leaving up to 2 rows behind
+        let board: BitBoard = "
+            XXXX______
+            XXXX______
+            XXXX______
+            XXXX______
+        "
+        .parse()
+        .unwrap();
+        let goal = LineClearGoal {
+            lines: 2,
+            leftover: Leftover::AtMost(2),
+        };
+        let queue = [I, L, J, O, T];
+        let all = solutions(&queue, board, goal, false);
+        assert!(!all.is_empty());
+        let mut combinations = HashSet::new();
+        for solution in &all {
+            let after = after(board, solution);
+            assert_eq!((0..4).filter(|&y| after.line_filled(y)).count(), 2);
+            assert_eq!(
+                after.lines_cleared().remove(BitBoard::filled(2)),
+                BitBoard(0)
+            );
+            let mut cells: Vec<_> = solution.iter().map(|p| p.board::<10>().0).collect();
+            cells.sort_unstable();
+            combinations.insert(cells);
+        }
+        // the fewest pieces that reach the goal are used
+        let fewest = all.iter().map(Vec::len).min().unwrap();
+        assert!(all.iter().all(|solution| solution.len() == fewest));
+        assert_eq!(
+            solutions(&queue, board, goal, true).len(),
+            combinations.len()
+        );
+    }
+}
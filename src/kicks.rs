@@ -0,0 +1,383 @@
+use crate::*;
+
+/// Describes how pieces spawn and rotate in a particular game.
+///
+/// Positions and kicks use the same coordinates as `SrsPiece`: pieces rotate around their
+/// center cell, so a rotation system that rotates some pieces around another point (e.g. the
+/// true center of the I piece in SRS) includes that correction in every kick.
+pub trait RotationSystem {
+    /// Offsets tried in order when rotating `piece` from `from` to `to`. The first offset that
+    /// moves the rotated piece to an unobstructed position is used.
+    fn kicks(&self, piece: Piece, from: Rotation, to: Rotation) -> &[(i32, i32)];
+
+    /// Position of a newly spawned piece, relative to the spawn position of the playfield.
+    fn spawn(&self, piece: Piece) -> SrsPiece {
+        SrsPiece {
+            piece,
+            rotation: Rotation::North,
+            x: 0,
+            y: 0,
+        }
+    }
+
+    fn has_180(&self) -> bool {
+        false
+    }
+}
+
+/// The Super Rotation System as described by the tetris guideline.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Srs;
+
+/// TETR.IO's SRS+: SRS with symmetric I piece kicks and 180 degree rotation.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SrsPlus;
+
+/// SRS as implemented by Arika (e.g. the TGM3 world rule), which uses different I piece kicks.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ArikaSrs;
+
+/// SRS rotation centers without any kicks. Rotations fail if the rotated piece is obstructed.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NoKicks;
+
+impl RotationSystem for Srs {
+    fn kicks(&self, piece: Piece, from: Rotation, to: Rotation) -> &[(i32, i32)] {
+        match piece {
+            Piece::O => &O_KICKS[from as usize][to as usize],
+            Piece::I => &I_KICKS[from as usize][to as usize],
+            _ => &JLSTZ_KICKS[from as usize][to as usize],
+        }
+    }
+}
+
+impl RotationSystem for SrsPlus {
+    fn kicks(&self, piece: Piece, from: Rotation, to: Rotation) -> &[(i32, i32)] {
+        if to == from.opposite() {
+            match piece {
+                Piece::O => &O_KICKS[from as usize][to as usize],
+                Piece::I => &I_180_KICKS[from as usize],
+                _ => &JLSTZ_180_KICKS[from as usize],
+            }
+        } else {
+            match piece {
+                Piece::O => &O_KICKS[from as usize][to as usize],
+                Piece::I => &SRS_PLUS_I_KICKS[from as usize][to as usize],
+                _ => &JLSTZ_KICKS[from as usize][to as usize],
+            }
+        }
+    }
+
+    fn has_180(&self) -> bool {
+        true
+    }
+}
+
+impl RotationSystem for ArikaSrs {
+    fn kicks(&self, piece: Piece, from: Rotation, to: Rotation) -> &[(i32, i32)] {
+        match piece {
+            Piece::O => &O_KICKS[from as usize][to as usize],
+            Piece::I => &ARIKA_I_KICKS[from as usize][to as usize],
+            _ => &JLSTZ_KICKS[from as usize][to as usize],
+        }
+    }
+}
+
+impl RotationSystem for NoKicks {
+    fn kicks(&self, piece: Piece, from: Rotation, to: Rotation) -> &[(i32, i32)] {
+        match piece {
+            Piece::O => &O_KICKS[from as usize][to as usize],
+            Piece::I => &I_KICKS[from as usize][to as usize][..1],
+            _ => &JLSTZ_KICKS[from as usize][to as usize][..1],
+        }
+    }
+}
+
+// SRS kicks are the difference between the offsets of the initial and final rotation states.
+// https://harddrop.com/wiki/SRS#How_Guideline_SRS_Really_Works
+const JLSTZ_OFFSETS: [[(i32, i32); 5]; 4] = [
+    [(0, 0), (0, 0), (0, 0), (0, 0), (0, 0)],
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    [(0, 0), (0, 0), (0, 0), (0, 0), (0, 0)],
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+];
+const I_OFFSETS: [[(i32, i32); 5]; 4] = [
+    [(0, 0), (-1, 0), (2, 0), (-1, 0), (2, 0)],
+    [(-1, 0), (0, 0), (0, 0), (0, 1), (0, -2)],
+    [(-1, 1), (1, 1), (-2, 1), (1, 0), (-2, 0)],
+    [(0, 1), (0, 1), (0, 1), (0, -1), (0, 2)],
+];
+const O_OFFSETS: [[(i32, i32); 1]; 4] = [[(0, 0)], [(0, -1)], [(-1, -1)], [(-1, 0)]];
+
+static JLSTZ_KICKS: [[[(i32, i32); 5]; 4]; 4] = offset_kicks(&JLSTZ_OFFSETS);
+static I_KICKS: [[[(i32, i32); 5]; 4]; 4] = offset_kicks(&I_OFFSETS);
+static O_KICKS: [[[(i32, i32); 1]; 4]; 4] = offset_kicks(&O_OFFSETS);
+
+// The following tables are written relative to the true rotation center, as they usually are
+// published, and then corrected for the I piece's rotation center.
+// https://tetris.wiki/TETR.IO#Rotation_system
+static SRS_PLUS_I_KICKS: [[[(i32, i32); 5]; 4]; 4] = centered(
+    &I_OFFSETS,
+    [
+        [
+            [(0, 0); 5],
+            [(0, 0), (1, 0), (-2, 0), (-2, -1), (1, 2)],
+            [(0, 0); 5],
+            [(0, 0), (-1, 0), (2, 0), (2, -1), (-1, 2)],
+        ],
+        [
+            [(0, 0), (-1, 0), (2, 0), (-1, -2), (2, 1)],
+            [(0, 0); 5],
+            [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+            [(0, 0); 5],
+        ],
+        [
+            [(0, 0); 5],
+            [(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)],
+            [(0, 0); 5],
+            [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+        ],
+        [
+            [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+            [(0, 0); 5],
+            [(0, 0), (1, 0), (-2, 0), (1, 2), (-2, -1)],
+            [(0, 0); 5],
+        ],
+    ],
+);
+
+// https://tetris.wiki/SRS#Arika_SRS
+static ARIKA_I_KICKS: [[[(i32, i32); 5]; 4]; 4] = centered(
+    &I_OFFSETS,
+    [
+        [
+            [(0, 0); 5],
+            [(0, 0), (-2, 0), (1, 0), (1, 2), (-2, -1)],
+            [(0, 0); 5],
+            [(0, 0), (2, 0), (-1, 0), (-1, 2), (2, -1)],
+        ],
+        [
+            [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+            [(0, 0); 5],
+            [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+            [(0, 0); 5],
+        ],
+        [
+            [(0, 0); 5],
+            [(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -1)],
+            [(0, 0); 5],
+            [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -1)],
+        ],
+        [
+            [(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)],
+            [(0, 0); 5],
+            [(0, 0), (1, 0), (-2, 0), (1, 2), (-2, -1)],
+            [(0, 0); 5],
+        ],
+    ],
+);
+
+// 180 degree kicks, indexed by the initial rotation state.
+static JLSTZ_180_KICKS: [[(i32, i32); 6]; 4] = SRS_PLUS_180;
+static I_180_KICKS: [[(i32, i32); 6]; 4] = centered_180(&I_OFFSETS, SRS_PLUS_180);
+const SRS_PLUS_180: [[(i32, i32); 6]; 4] = [
+    [(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)],
+    [(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)],
+    [(0, 0), (0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)],
+    [(0, 0), (-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)],
+];
+
+const fn offset_kicks<const N: usize>(offsets: &[[(i32, i32); N]; 4]) -> [[[(i32, i32); N]; 4]; 4] {
+    let mut kicks = [[[(0, 0); N]; 4]; 4];
+    let mut from = 0;
+    while from < 4 {
+        let mut to = 0;
+        while to < 4 {
+            let mut i = 0;
+            while i < N {
+                kicks[from][to][i] = (
+                    offsets[from][i].0 - offsets[to][i].0,
+                    offsets[from][i].1 - offsets[to][i].1,
+                );
+                i += 1;
+            }
+            to += 1;
+        }
+        from += 1;
+    }
+    kicks
+}
+
+const fn centered<const N: usize>(
+    offsets: &[[(i32, i32); N]; 4],
+    mut kicks: [[[(i32, i32); N]; 4]; 4],
+) -> [[[(i32, i32); N]; 4]; 4] {
+    let mut from = 0;
+    while from < 4 {
+        let mut to = 0;
+        while to < 4 {
+            let mut i = 0;
+            while i < N {
+                kicks[from][to][i].0 += offsets[from][0].0 - offsets[to][0].0;
+                kicks[from][to][i].1 += offsets[from][0].1 - offsets[to][0].1;
+                i += 1;
+            }
+            to += 1;
+        }
+        from += 1;
+    }
+    kicks
+}
+
+const fn centered_180<const N: usize>(
+    offsets: &[[(i32, i32); 5]; 4],
+    mut kicks: [[(i32, i32); N]; 4],
+) -> [[(i32, i32); N]; 4] {
+    let mut from = 0;
+    while from < 4 {
+        let to = (from + 2) % 4;
+        let mut i = 0;
+        while i < N {
+            kicks[from][i].0 += offsets[from][0].0 - offsets[to][0].0;
+            kicks[from][i].1 += offsets[from][0].1 - offsets[to][0].1;
+            i += 1;
+        }
+        from += 1;
+    }
+    kicks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Rotation::*;
+
+    /// The kicks relative to the first one, which is the rotation center correction.
+    fn relative(kicks: &[(i32, i32)]) -> Vec<(i32, i32)> {
+        kicks
+            .iter()
+            .map(|&(x, y)| (x - kicks[0].0, y - kicks[0].1))
+            .collect()
+    }
+
+    fn mirror(rotation: Rotation) -> Rotation {
+        match rotation {
+            East => West,
+            West => East,
+            r => r,
+        }
+    }
+
+    // https://harddrop.com/wiki/SRS#Wall_Kicks
+    const QUARTER_TURNS: [(Rotation, Rotation); 8] = [
+        (North, East),
+        (East, North),
+        (East, South),
+        (South, East),
+        (South, West),
+        (West, South),
+        (West, North),
+        (North, West),
+    ];
+    const GUIDELINE_JLSTZ: [[(i32, i32); 5]; 8] = [
+        [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+        [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+        [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+        [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+        [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+        [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+        [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+        [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    ];
+    const GUIDELINE_I: [[(i32, i32); 5]; 8] = [
+        [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+        [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+        [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+        [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+        [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+        [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+        [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+        [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+    ];
+
+    #[test]
+    fn srs_matches_guideline() {
+        for (&(from, to), (jlstz, i)) in QUARTER_TURNS
+            .iter()
+            .zip(GUIDELINE_JLSTZ.iter().zip(&GUIDELINE_I))
+        {
+            for piece in [Piece::J, Piece::L, Piece::S, Piece::T, Piece::Z] {
+                assert_eq!(Srs.kicks(piece, from, to), jlstz);
+            }
+            assert_eq!(relative(Srs.kicks(Piece::I, from, to)), i);
+            assert_eq!(Srs.kicks(Piece::O, from, to).len(), 1);
+        }
+        assert!(!Srs.has_180());
+    }
+
+    #[test]
+    fn i_rotates_around_its_true_center() {
+        // the I piece's center cell moves right when it rotates clockwise from spawn
+        assert_eq!(Srs.kicks(Piece::I, North, East)[0], (1, 0));
+        assert_eq!(Srs.kicks(Piece::I, East, North)[0], (-1, 0));
+        assert_eq!(SrsPlus.kicks(Piece::I, North, East)[0], (1, 0));
+        assert_eq!(SrsPlus.kicks(Piece::I, North, South)[0], (1, -1));
+    }
+
+    #[test]
+    fn srs_plus_i_kicks_are_symmetric() {
+        assert_eq!(
+            relative(SrsPlus.kicks(Piece::I, North, East)),
+            [(0, 0), (1, 0), (-2, 0), (-2, -1), (1, 2)]
+        );
+        for &(from, to) in &QUARTER_TURNS {
+            let mirrored: Vec<_> = relative(SrsPlus.kicks(Piece::I, mirror(from), mirror(to)))
+                .into_iter()
+                .map(|(x, y)| (-x, y))
+                .collect();
+            assert_eq!(relative(SrsPlus.kicks(Piece::I, from, to)), mirrored);
+        }
+    }
+
+    #[test]
+    fn srs_plus_keeps_srs_quarter_turns_for_other_pieces() {
+        for &(from, to) in &QUARTER_TURNS {
+            for piece in [Piece::J, Piece::L, Piece::S, Piece::T, Piece::Z, Piece::O] {
+                assert_eq!(SrsPlus.kicks(piece, from, to), Srs.kicks(piece, from, to));
+                assert_eq!(ArikaSrs.kicks(piece, from, to), Srs.kicks(piece, from, to));
+            }
+        }
+    }
+
+    #[test]
+    fn srs_plus_180_kicks() {
+        assert!(SrsPlus.has_180());
+        // https://tetris.wiki/TETR.IO#Rotation_system
+        assert_eq!(
+            SrsPlus.kicks(Piece::T, North, South),
+            [(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)]
+        );
+        assert_eq!(
+            SrsPlus.kicks(Piece::T, East, West),
+            [(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)]
+        );
+        for from in [North, East, South, West] {
+            assert_eq!(
+                relative(SrsPlus.kicks(Piece::I, from, from.opposite())),
+                SrsPlus.kicks(Piece::T, from, from.opposite())
+            );
+        }
+    }
+
+    #[test]
+    fn no_kicks_only_tries_the_rotation_center() {
+        for &(from, to) in &QUARTER_TURNS {
+            for piece in PIECES {
+                assert_eq!(
+                    NoKicks.kicks(piece, from, to),
+                    &Srs.kicks(piece, from, to)[..1]
+                );
+            }
+        }
+    }
+}
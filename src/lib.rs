@@ -1,11 +1,35 @@
 use arrayvec::ArrayVec;
 
+mod chain;
 mod combination;
+mod cover;
+pub mod finesse;
+#[cfg(feature = "fumen")]
+pub mod fumen;
+mod goal;
+pub mod kicks;
+mod pattern;
+mod percent;
 pub mod placeability;
+pub mod polyomino;
+pub mod randomizer;
+pub mod score;
+mod setup;
 mod solve;
+pub mod spins;
+mod strategy;
+mod text;
 
+pub use chain::*;
 pub use combination::*;
+pub use cover::*;
+pub use goal::*;
+pub use pattern::*;
+pub use percent::*;
+pub use setup::*;
 pub use solve::*;
+pub use strategy::*;
+pub use text::*;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum Piece {
@@ -40,7 +64,7 @@ pub const PIECES: [Piece; 7] = [
     Piece::I,
 ];
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
 pub struct PieceSet(pub [u8; 7]);
 
 impl PieceSet {
@@ -62,18 +86,12 @@ impl PieceSet {
     }
 }
 
-impl Default for PieceSet {
-    fn default() -> Self {
-        PieceSet([0; 7])
-    }
-}
-
 impl std::fmt::Display for PieceSet {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         const NAMES: [char; 7] = ['S', 'Z', 'J', 'L', 'T', 'O', 'I'];
-        for i in 0..7 {
-            for _ in 0..self.0[i] {
-                write!(f, "{}", NAMES[i])?;
+        for (&name, &count) in NAMES.iter().zip(self.0.iter()) {
+            for _ in 0..count {
+                write!(f, "{}", name)?;
             }
         }
         Ok(())
@@ -90,13 +108,46 @@ impl std::iter::FromIterator<Piece> for PieceSet {
     }
 }
 
+/// The most rows a `BitBoard` can hold, which is how many a board of the standard width holds.
+pub const MAX_HEIGHT: usize = 12;
+
+/// A board of `W` columns, with bit `x + W * y` set if the cell at column `x` and row `y`
+/// (counting up from the bottom) is filled.
+///
+/// Widths other than the standard 10 are for variant games, and can be anything from 4 to 12.
+/// Other widths are rejected at compile time:
+///
+/// ```compile_fail
+/// # use pcf::{placeability, BitBoard, Piece};
+/// let board: BitBoard<3> = BitBoard(0);
+/// placeability::placements(board, Piece::T, placeability::always);
+/// ```
+///
+/// Everything works at any width except the `simple_srs_spins` judge and the fumen format, which
+/// only support the standard width. Rust doesn't use the default width to infer the width of a
+/// board such as `BitBoard(0)`, so unless something else passed with it fixes the width, give
+/// the board a type such as `let board: BitBoard = BitBoard(0);`, where the default applies.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Default)]
-pub struct BitBoard(pub u64);
+pub struct BitBoard<const W: usize = 10>(pub u128);
+
+impl<const W: usize> BitBoard<W> {
+    /// Number of rows the board can hold. This is `MAX_HEIGHT` unless the rows are too wide to
+    /// fit that many in 128 bits.
+    pub const HEIGHT: usize = {
+        let () = WidthTables::<W>::SUPPORTED;
+        if W * MAX_HEIGHT <= 128 {
+            MAX_HEIGHT
+        } else {
+            128 / W
+        }
+    };
+
+    /// The cells of the bottom row.
+    const ROW: u128 = (1 << W) - 1;
 
-impl BitBoard {
     #[inline]
-    pub fn filled(height: usize) -> BitBoard {
-        BitBoard((1 << height * 10) - 1)
+    pub fn filled(height: usize) -> Self {
+        BitBoard((1 << (height * W)) - 1)
     }
 
     #[inline]
@@ -116,21 +167,21 @@ impl BitBoard {
 
     #[inline]
     pub fn cell_filled(self, x: usize, y: usize) -> bool {
-        y < 6 && self.0 & 1 << x + y * 10 != 0
+        y < Self::HEIGHT && self.0 & 1 << (x + y * W) != 0
     }
 
     #[inline]
     pub fn line_filled(self, y: usize) -> bool {
-        self.0 >> 10 * y & (1 << 10) - 1 == (1 << 10) - 1
+        self.0 >> (W * y) & Self::ROW == Self::ROW
     }
 
     #[inline]
-    pub fn lines_cleared(self) -> BitBoard {
+    pub fn lines_cleared(self) -> Self {
         let mut b = 0;
         let mut row = 0;
-        for y in 0..6 {
+        for y in 0..Self::HEIGHT {
             if !self.line_filled(y) {
-                b |= (self.0 >> 10 * y & (1 << 10) - 1) << 10 * row;
+                b |= (self.0 >> (W * y) & Self::ROW) << (W * row);
                 row += 1;
             }
         }
@@ -140,9 +191,9 @@ impl BitBoard {
     #[inline]
     pub fn leftmost_empty_column(self, height: usize) -> usize {
         // start with completely filled row
-        let mut collapsed = (1 << 10) - 1;
+        let mut collapsed = Self::ROW;
         for i in 0..height {
-            collapsed &= self.0 >> i * 10;
+            collapsed &= self.0 >> (i * W);
         }
         // collapsed has a 0 wherever there's an empty cell in rows 0..height
         // so to find the x of first one, we need only count the number of 1s before it
@@ -158,48 +209,48 @@ pub struct Placement {
 
 impl Placement {
     #[inline]
-    pub fn board(self) -> BitBoard {
-        BitBoard(self.kind.board().0 << self.x)
+    pub fn board<const W: usize>(self) -> BitBoard<W> {
+        BitBoard(self.kind.board::<W>().0 << self.x)
     }
 
     #[inline]
-    pub fn supported_after_clears(self, mut on: BitBoard) -> bool {
-        let hurdled_lines = HURDLE_MASKS[self.kind.hurdles() as usize];
+    pub fn supported_after_clears<const W: usize>(self, mut on: BitBoard<W>) -> bool {
+        let hurdled_lines = WidthTables::<W>::HURDLE_MASKS[self.kind.hurdles() as usize];
         if BitBoard(hurdled_lines).remove(on) != BitBoard(0) {
             // hurdled lines not filled means the hurdled placement is impossible
             false
         } else {
             // copy lines below filled lines into filled lines
-            for y in 1..6 {
+            for y in 1..BitBoard::<W>::HEIGHT {
                 if on.line_filled(y) {
-                    on.0 &= (on.0 << 10) | !((1 << 10) - 1 << 10 * y);
+                    on.0 &= (on.0 << W) | !(BitBoard::<W>::ROW << (W * y));
                 }
             }
-            self.kind.y() == 0 || on.overlaps(BitBoard(self.kind.below_mask().0 << self.x))
+            self.kind.y() == 0 || on.overlaps(BitBoard(self.kind.below_mask::<W>().0 << self.x))
         }
     }
 
     #[inline]
-    pub fn supported_without_clears(self, on: BitBoard) -> bool {
-        let hurdled_lines = HURDLE_MASKS[self.kind.hurdles() as usize];
+    pub fn supported_without_clears<const W: usize>(self, on: BitBoard<W>) -> bool {
+        let hurdled_lines = WidthTables::<W>::HURDLE_MASKS[self.kind.hurdles() as usize];
         if BitBoard(hurdled_lines).remove(on) != BitBoard(0) {
             // hurdled lines not filled means the hurdled placement is impossible
             false
         } else {
-            self.kind.y() == 0 || on.overlaps(BitBoard(self.kind.below_mask().0 << self.x))
+            self.kind.y() == 0 || on.overlaps(BitBoard(self.kind.below_mask::<W>().0 << self.x))
         }
     }
 
     #[inline]
-    pub fn harddrop_mask(self) -> BitBoard {
-        BitBoard(self.kind.harddrop_mask().0 << self.x)
+    pub fn harddrop_mask<const W: usize>(self) -> BitBoard<W> {
+        BitBoard(self.kind.harddrop_mask::<W>().0 << self.x)
     }
 
     #[inline]
-    pub fn srs_piece(self, board: BitBoard) -> ArrayVec<[SrsPiece; 4]> {
+    pub fn srs_piece<const W: usize>(self, board: BitBoard<W>) -> ArrayVec<[SrsPiece; 4]> {
         let mut below_lines = 0;
-        for i in 0..self.kind.y() {
-            if board.0 >> 10 * i & (1 << 10) - 1 == (1 << 10) - 1 {
+        for i in 0..self.kind.y() as usize {
+            if board.line_filled(i) {
                 below_lines += 1;
             }
         }
@@ -223,6 +274,38 @@ pub enum Rotation {
     West,
 }
 
+impl Rotation {
+    #[inline]
+    pub fn cw(self) -> Rotation {
+        match self {
+            Rotation::North => Rotation::East,
+            Rotation::East => Rotation::South,
+            Rotation::South => Rotation::West,
+            Rotation::West => Rotation::North,
+        }
+    }
+
+    #[inline]
+    pub fn ccw(self) -> Rotation {
+        match self {
+            Rotation::North => Rotation::West,
+            Rotation::East => Rotation::North,
+            Rotation::South => Rotation::East,
+            Rotation::West => Rotation::South,
+        }
+    }
+
+    #[inline]
+    pub fn opposite(self) -> Rotation {
+        match self {
+            Rotation::North => Rotation::South,
+            Rotation::East => Rotation::West,
+            Rotation::South => Rotation::North,
+            Rotation::West => Rotation::East,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct SrsPiece {
     pub piece: Piece,
@@ -231,19 +314,109 @@ pub struct SrsPiece {
     pub y: i32,
 }
 
+impl SrsPiece {
+    /// The cells occupied by this piece. `x` and `y` are the position of the piece's center
+    /// cell; the other cells are placed according to the SRS true rotation.
+    #[inline]
+    pub fn cells(self) -> [(i32, i32); 4] {
+        let mut cells = match self.piece {
+            Piece::S => [(-1, 0), (0, 0), (0, 1), (1, 1)],
+            Piece::Z => [(-1, 1), (0, 1), (0, 0), (1, 0)],
+            Piece::J => [(-1, 1), (-1, 0), (0, 0), (1, 0)],
+            Piece::L => [(-1, 0), (0, 0), (1, 0), (1, 1)],
+            Piece::T => [(-1, 0), (0, 0), (1, 0), (0, 1)],
+            Piece::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
+            Piece::I => [(-1, 0), (0, 0), (1, 0), (2, 0)],
+        };
+        for (x, y) in &mut cells {
+            let (rx, ry) = match self.rotation {
+                Rotation::North => (*x, *y),
+                Rotation::East => (*y, -*x),
+                Rotation::South => (-*x, -*y),
+                Rotation::West => (-*y, *x),
+            };
+            *x = rx + self.x;
+            *y = ry + self.y;
+        }
+        cells
+    }
+
+    /// The placement this piece occupies on `board`. The position of this piece is relative to
+    /// the board after line clears, like the positions returned by `Placement::srs_piece`.
+    pub fn placement<const W: usize>(self, board: BitBoard<W>) -> Option<Placement> {
+        let mut rows = ArrayVec::<[usize; MAX_HEIGHT]>::new();
+        for y in 0..BitBoard::<W>::HEIGHT {
+            if !board.line_filled(y) {
+                rows.push(y);
+            }
+        }
+
+        let mut bits = 0;
+        let mut left = W;
+        for &(x, y) in &self.cells() {
+            if !(0..W as i32).contains(&x) || y < 0 || y as usize >= rows.len() {
+                return None;
+            }
+            bits |= 1 << (x as usize + W * rows[y as usize]);
+            left = left.min(x as usize);
+        }
+
+        let y = (0..BitBoard::<W>::HEIGHT).find(|&y| bits & 1 << (left + W * y) != 0)?;
+        data::PIECE_STATES_BY_HEIGHT_KIND_CELLY[BitBoard::<W>::HEIGHT - 1][self.piece as usize][y]
+            .iter()
+            .map(|&kind| Placement {
+                kind,
+                x: left as u8,
+            })
+            .find(|p| p.board() == BitBoard::<W>(bits))
+    }
+}
+
 pub use data::PieceState;
 include!(concat!(env!("OUT_DIR"), "/data.rs"));
 
-const HURDLE_MASKS: [u64; 64] = {
-    let mut result = [0; 64];
+const STATES: usize = data::PIECE_BITS.len();
+
+/// Tables for boards of width `W`, built at compile time from the generated tables, which are
+/// for the standard width.
+struct WidthTables<const W: usize>;
+
+impl<const W: usize> WidthTables<W> {
+    /// Fails to compile for unsupported widths. Checked by `BitBoard::HEIGHT`, which everything
+    /// that works with boards uses.
+    const SUPPORTED: () = assert!(W >= 4 && W <= 12, "boards must be 4 to 12 columns wide");
+    const PIECE_BITS: &'static [u128; STATES] = &restride::<W>(data::PIECE_BITS);
+    const PIECE_BELOW: &'static [u128; STATES] = &restride::<W>(data::PIECE_BELOW);
+    const PIECE_HARDDROP: &'static [u128; STATES] = &restride::<W>(data::PIECE_HARDDROP);
+    const HURDLE_MASKS: &'static [u128; 1 << MAX_HEIGHT] = &hurdle_masks::<W>();
+}
+
+/// Moves the rows of each board in `boards` from the standard width to `W`. Rows that don't fit
+/// are dropped; they can only be reached by PCs taller than the board can hold.
+const fn restride<const W: usize>(boards: &[u128; STATES]) -> [u128; STATES] {
+    let mut result = [0; STATES];
+    let mut i = 0;
+    while i < STATES {
+        let mut y = 0;
+        while y < BitBoard::<W>::HEIGHT {
+            result[i] |= ((boards[i] >> (10 * y)) & ((1 << 10) - 1)) << (W * y);
+            y += 1;
+        }
+        i += 1;
+    }
+    result
+}
+
+const fn hurdle_masks<const W: usize>() -> [u128; 1 << MAX_HEIGHT] {
+    let mut result = [0; 1 << MAX_HEIGHT];
     let mut hurdle_mask = 0;
-    while hurdle_mask < 64 {
+    while hurdle_mask < 1 << MAX_HEIGHT {
         let mut hurdled_lines = 0;
-        let mut i = 5;
+        let mut i = BitBoard::<W>::HEIGHT as i32 - 1;
         while i >= 0 {
-            hurdled_lines <<= 10;
+            hurdled_lines <<= W;
             if hurdle_mask & 1 << i != 0 {
-                hurdled_lines |= (1 << 10) - 1;
+                hurdled_lines |= BitBoard::<W>::ROW;
             }
             i -= 1;
         }
@@ -251,4 +424,4 @@ const HURDLE_MASKS: [u64; 64] = {
         hurdle_mask += 1;
     }
     result
-};
+}
@@ -0,0 +1,321 @@
+use crate::*;
+
+/// A set of piece sequences described in the pattern notation used by solution-finder.
+///
+/// A pattern is a list of elements, optionally separated by commas. Each element is one of:
+///
+/// - a piece letter such as `T`, which is always that piece,
+/// - `*`, which is any one piece,
+/// - a bag such as `[IOSZ]`, which is any one piece from the bag, or `[^T]`, which is any one
+///   piece not in the brackets,
+/// - a bag followed by `pN`, which is any `N` distinct draws from the bag in any order (`*p7`),
+/// - a bag followed by `!`, which is every order of the whole bag (`[IOSZ]!`).
+///
+/// A bag may contain a piece more than once, in which case it can be drawn more than once. The
+/// sequences of a pattern are distinct even when a bag contains duplicates.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Pattern {
+    elements: Vec<(PieceSet, usize)>,
+}
+
+impl Pattern {
+    /// Number of pieces in every sequence of this pattern.
+    pub fn len(&self) -> usize {
+        self.elements.iter().map(|&(_, draws)| draws).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of distinct sequences this pattern describes.
+    pub fn count(&self) -> usize {
+        self.elements
+            .iter()
+            .map(|&(bag, draws)| count_draws(bag, draws))
+            .product()
+    }
+
+    /// Iterates over the distinct sequences this pattern describes, in lexicographic order by
+    /// piece index.
+    pub fn sequences(&self) -> Sequences {
+        let mut choices = vec![];
+        for &(bag, draws) in &self.elements {
+            let mut element = vec![];
+            all_draws(bag, draws, &mut vec![], &mut element);
+            choices.push(element);
+        }
+        let done = choices.iter().any(Vec::is_empty);
+        Sequences {
+            indices: vec![0; choices.len()],
+            choices,
+            done,
+        }
+    }
+}
+
+impl std::str::FromStr for Pattern {
+    type Err = ParsePatternError;
+
+    fn from_str(s: &str) -> Result<Self, ParsePatternError> {
+        let mut chars = s.chars().filter(|c| !c.is_whitespace()).peekable();
+        let mut elements = vec![];
+        while chars.peek().is_some() {
+            let (bag, single) = parse_bag(&mut chars)?;
+            let size = bag.0.iter().map(|&c| c as usize).sum();
+            let draws = match chars.peek() {
+                Some('p') if !single => {
+                    chars.next();
+                    let mut n = None;
+                    while let Some(d) = chars.peek().and_then(|c| c.to_digit(10)) {
+                        chars.next();
+                        n = Some(n.unwrap_or(0) * 10 + d as usize);
+                    }
+                    n.filter(|&n| n <= size).ok_or(ParsePatternError)?
+                }
+                Some('!') if !single => {
+                    chars.next();
+                    size
+                }
+                _ => 1,
+            };
+            if size == 0 {
+                return Err(ParsePatternError);
+            }
+            elements.push((bag, draws));
+            if chars.peek() == Some(&',') {
+                chars.next();
+                if chars.peek().is_none() {
+                    return Err(ParsePatternError);
+                }
+            }
+        }
+        Ok(Pattern { elements })
+    }
+}
+
+impl std::str::FromStr for PieceSet {
+    type Err = ParsePatternError;
+
+    /// Parses a multiset of pieces from bag notation. Piece letters add that piece, `*` adds one
+    /// of each piece, and bags in brackets such as `[IOSZ]` or `[^T]` add the pieces of the bag,
+    /// so `*T` is a full bag with an extra T.
+    fn from_str(s: &str) -> Result<Self, ParsePatternError> {
+        let mut chars = s.chars().filter(|c| !c.is_whitespace()).peekable();
+        let mut set = PieceSet::default();
+        while chars.peek().is_some() {
+            let (bag, _) = parse_bag(&mut chars)?;
+            for (count, added) in set.0.iter_mut().zip(bag.0.iter()) {
+                *count += added;
+            }
+        }
+        Ok(set)
+    }
+}
+
+/// Error returned when parsing a pattern or bag fails.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ParsePatternError;
+
+impl std::fmt::Display for ParsePatternError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "invalid piece pattern")
+    }
+}
+
+impl std::error::Error for ParsePatternError {}
+
+/// Iterator over the sequences of a `Pattern`.
+pub struct Sequences {
+    choices: Vec<Vec<Vec<Piece>>>,
+    indices: Vec<usize>,
+    done: bool,
+}
+
+impl Iterator for Sequences {
+    type Item = Vec<Piece>;
+
+    fn next(&mut self) -> Option<Vec<Piece>> {
+        if self.done {
+            return None;
+        }
+        let mut sequence = vec![];
+        for (choices, &i) in self.choices.iter().zip(self.indices.iter()) {
+            sequence.extend_from_slice(&choices[i]);
+        }
+
+        // advance to the next combination of choices, last element fastest
+        self.done = true;
+        for (choices, i) in self.choices.iter().zip(self.indices.iter_mut()).rev() {
+            *i += 1;
+            if *i < choices.len() {
+                self.done = false;
+                break;
+            }
+            *i = 0;
+        }
+        Some(sequence)
+    }
+}
+
+fn parse_piece(c: char) -> Option<Piece> {
+    Some(match c {
+        'S' => Piece::S,
+        'Z' => Piece::Z,
+        'J' => Piece::J,
+        'L' => Piece::L,
+        'T' => Piece::T,
+        'O' => Piece::O,
+        'I' => Piece::I,
+        _ => return None,
+    })
+}
+
+/// Parses a piece letter, `*` or bracketed bag. The returned flag is true for piece letters,
+/// which can't be followed by `pN` or `!`.
+fn parse_bag(
+    chars: &mut std::iter::Peekable<impl Iterator<Item = char>>,
+) -> Result<(PieceSet, bool), ParsePatternError> {
+    match chars.next().ok_or(ParsePatternError)? {
+        '*' => Ok((PieceSet([1; 7]), false)),
+        '[' => {
+            let negated = chars.peek() == Some(&'^');
+            if negated {
+                chars.next();
+            }
+            let mut bag = PieceSet::default();
+            loop {
+                match chars.next().ok_or(ParsePatternError)? {
+                    ']' => break,
+                    c => bag = bag.with(parse_piece(c).ok_or(ParsePatternError)?),
+                }
+            }
+            if negated {
+                bag = PIECES
+                    .iter()
+                    .copied()
+                    .filter(|&p| !bag.contains(p))
+                    .collect();
+            }
+            Ok((bag, false))
+        }
+        c => Ok((
+            PieceSet::default().with(parse_piece(c).ok_or(ParsePatternError)?),
+            true,
+        )),
+    }
+}
+
+fn count_draws(bag: PieceSet, draws: usize) -> usize {
+    if draws == 0 {
+        return 1;
+    }
+    PIECES
+        .iter()
+        .filter(|&&p| bag.contains(p))
+        .map(|&p| count_draws(bag.without(p), draws - 1))
+        .sum()
+}
+
+fn all_draws(bag: PieceSet, draws: usize, current: &mut Vec<Piece>, into: &mut Vec<Vec<Piece>>) {
+    if current.len() == draws {
+        into.push(current.clone());
+        return;
+    }
+    for &p in &PIECES {
+        if bag.contains(p) {
+            current.push(p);
+            all_draws(bag.without(p), draws, current, into);
+            current.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Piece::*;
+
+    fn sequences(pattern: &str) -> Vec<Vec<Piece>> {
+        pattern.parse::<Pattern>().unwrap().sequences().collect()
+    }
+
+    #[test]
+    fn single_elements() {
+        assert_eq!(sequences("T"), [[T]]);
+        assert_eq!(
+            sequences("*"),
+            PIECES.iter().map(|&p| [p]).collect::<Vec<_>>()
+        );
+        assert_eq!(sequences("[IOSZ]"), [[S], [Z], [O], [I]]);
+        assert_eq!(sequences("[^SZJLT]"), [[O], [I]]);
+        assert_eq!(sequences("T, [IO] J"), [[T, O, J], [T, I, J]]);
+    }
+
+    #[test]
+    fn draws_and_permutations() {
+        let bag: Pattern = "*p7".parse().unwrap();
+        assert_eq!(bag.len(), 7);
+        assert_eq!(bag.count(), 5040);
+        assert_eq!(bag.sequences().next().unwrap(), PIECES);
+        assert_eq!(sequences("[SZ]!"), [[S, Z], [Z, S]]);
+        assert_eq!(sequences("[IOSZ]p1"), sequences("[IOSZ]"));
+        assert_eq!("*p3,T".parse::<Pattern>().unwrap().count(), 210);
+    }
+
+    #[test]
+    fn duplicate_pieces_in_a_bag() {
+        assert_eq!(sequences("[TT]p2"), [[T, T]]);
+        assert_eq!(sequences("[IIO]p2"), [[O, I], [I, O], [I, I]]);
+        assert_eq!("[IIO]!".parse::<Pattern>().unwrap().count(), 3);
+    }
+
+    #[test]
+    fn count_matches_sequences() {
+        for pattern in ["*p4", "[^T]p3,*", "[TTIO]!", "I,*p2,[SZ]p2", ""] {
+            let parsed: Pattern = pattern.parse().unwrap();
+            let sequences: Vec<_> = parsed.sequences().collect();
+            assert_eq!(sequences.len(), parsed.count(), "{}", pattern);
+            assert!(sequences.iter().all(|s| s.len() == parsed.len()));
+            let indices = |s: &[Piece]| s.iter().map(|&p| p as usize).collect::<Vec<_>>();
+            assert!(
+                sequences
+                    .windows(2)
+                    .all(|w| indices(&w[0]) < indices(&w[1])),
+                "{}",
+                pattern
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_bad_patterns() {
+        for pattern in [
+            "X",
+            "Tp2",
+            "T!",
+            "*p8",
+            "*p",
+            "[IO",
+            "[]",
+            "[^SZJLTOI]",
+            "T,",
+            "T,,I",
+        ] {
+            assert_eq!(
+                pattern.parse::<Pattern>(),
+                Err(ParsePatternError),
+                "{}",
+                pattern
+            );
+        }
+    }
+
+    #[test]
+    fn piece_sets() {
+        assert_eq!("*T".parse(), Ok(PieceSet([1, 1, 1, 1, 2, 1, 1])));
+        assert_eq!("[^T] I".parse(), Ok(PieceSet([1, 1, 1, 1, 0, 1, 2])));
+        assert_eq!("TTT".parse(), Ok(PieceSet([0, 0, 0, 0, 3, 0, 0])));
+        assert_eq!("T*p2".parse::<PieceSet>(), Err(ParsePatternError));
+    }
+}
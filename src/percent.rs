@@ -0,0 +1,197 @@
+use crate::combination::Tetrominoes;
+use crate::solve::{pc_heights, solve_placement_combo, PieceSequence, DEFAULT_HEIGHTS};
+use crate::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Number of queues that can perfect clear out of some total.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Rate {
+    pub successes: usize,
+    pub total: usize,
+}
+
+impl Rate {
+    /// The fraction of queues that can perfect clear, or 0 if there are no queues.
+    pub fn rate(self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.successes as f64 / self.total as f64
+        }
+    }
+
+    fn add(&mut self, success: bool) {
+        self.total += 1;
+        self.successes += success as usize;
+    }
+}
+
+/// The result of a `percent` calculation.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Percent {
+    pub overall: Rate,
+    /// Rates for queues starting with each piece, indexed by `Piece as usize`.
+    pub by_first_piece: [Rate; 7],
+    /// Queues that can't perfect clear, in the order they were given.
+    pub failures: Vec<Vec<Piece>>,
+}
+
+/// Determines which of `queues` can perfect clear the board, as `solve_pc` would.
+///
+/// Combinations are found once per PC height using every piece any queue could use, and then
+/// checked against each queue, so this is much faster than calling `solve_pc` for each queue.
+/// Queues that only differ in pieces that can't be used are only checked once. Patterns can be
+/// passed as `pattern.sequences()`.
+///
+/// If `abort` is set, queues that have not been checked yet are counted as failures.
+pub fn percent<const W: usize>(
+    board: BitBoard<W>,
+    queues: impl IntoIterator<Item = impl AsRef<[Piece]>>,
+    hold_allowed: bool,
+    abort: &AtomicBool,
+    placeability_judge: impl Fn(BitBoard<W>, Placement) -> bool,
+) -> Percent {
+    let queues: Vec<_> = queues.into_iter().collect();
+    let mut succeeded = vec![false; queues.len()];
+
+    for (height, pieces) in pc_heights(board, Tetrominoes::CELLS)
+        .into_iter()
+        .filter(|(height, _)| DEFAULT_HEIGHTS.contains(height))
+    {
+        let usable = |queue: &[Piece]| -> PieceSequence {
+            queue
+                .iter()
+                .copied()
+                .take(pieces + hold_allowed as usize)
+                .collect()
+        };
+
+        let mut piece_set = PieceSet::default();
+        for (queue, _) in queues
+            .iter()
+            .zip(&succeeded)
+            .filter(|&(q, &s)| !s && q.as_ref().len() >= pieces)
+        {
+            for (max, count) in piece_set
+                .0
+                .iter_mut()
+                .zip(&usable(queue.as_ref()).to_set().0)
+            {
+                *max = (*max).max(*count);
+            }
+        }
+        if piece_set == PieceSet::default() {
+            continue;
+        }
+
+        let mut combos = vec![];
+        find_combinations(piece_set, board, abort, height, |combo| {
+            let set: PieceSet = combo.iter().map(|p| p.kind.piece()).collect();
+            combos.push((set, combo.to_vec()));
+        });
+
+        let mut cache = HashMap::new();
+        for (queue, succeeded) in queues.iter().zip(&mut succeeded) {
+            let queue = queue.as_ref();
+            if *succeeded || queue.len() < pieces || abort.load(Ordering::Acquire) {
+                continue;
+            }
+            let sequence = usable(queue);
+            *succeeded = *cache.entry(sequence).or_insert_with(|| {
+                let available = sequence.to_set();
+                combos
+                    .iter()
+                    .filter(|(set, _)| set.0.iter().zip(&available.0).all(|(n, a)| n <= a))
+                    .any(|(_, combo)| {
+                        let mut found = false;
+                        solve_placement_combo(
+                            Tetrominoes,
+                            sequence,
+                            board,
+                            combo,
+                            hold_allowed,
+                            true,
+                            &placeability_judge,
+                            |_| found = true,
+                        );
+                        found
+                    })
+            });
+        }
+    }
+
+    let mut result = Percent::default();
+    for (queue, &succeeded) in queues.iter().zip(&succeeded) {
+        let queue = queue.as_ref();
+        result.overall.add(succeeded);
+        if let Some(&first) = queue.first() {
+            result.by_first_piece[first as usize].add(succeeded);
+        }
+        if !succeeded {
+            result.failures.push(queue.to_vec());
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Piece::*;
+
+    #[test]
+    fn counts_queues_that_can_clear() {
+        let board: BitBoard = "
+            XXXX______
+            XXXX______
+        "
+        .parse()
+        .unwrap();
+        let queues = [[O, I, I], [T, T, T], [I, O, I], [S, S, S]];
+        let result = percent(
+            board,
+            queues,
+            true,
+            &AtomicBool::new(false),
+            placeability::always,
+        );
+
+        assert_eq!(
+            result.overall,
+            Rate {
+                successes: 2,
+                total: 4
+            }
+        );
+        assert_eq!(result.overall.rate(), 0.5);
+        assert_eq!(
+            result.by_first_piece[I as usize],
+            Rate {
+                successes: 1,
+                total: 1
+            }
+        );
+        assert_eq!(result.by_first_piece[Z as usize], Rate::default());
+        assert_eq!(result.failures, vec![vec![T, T, T], vec![S, S, S]]);
+    }
+
+    #[test]
+    fn counts_unchecked_queues_as_failures() {
+        let board: BitBoard = "
+            XXXX______
+            XXXX______
+        "
+        .parse()
+        .unwrap();
+        let result = percent(
+            board,
+            [[O, I, I]],
+            true,
+            &AtomicBool::new(true),
+            placeability::always,
+        );
+        assert_eq!(result.overall.successes, 0);
+        assert_eq!(result.failures, vec![vec![O, I, I]]);
+    }
+}
@@ -1,20 +1,22 @@
+use crate::kicks::{RotationSystem, Srs};
 use crate::*;
+use std::collections::HashSet;
 
-pub fn always(_: BitBoard, _: Placement) -> bool {
+pub fn always<const W: usize>(_: BitBoard<W>, _: Placement) -> bool {
     true
 }
 
-pub fn hard_drop_only(mut board: BitBoard, placement: Placement) -> bool {
-    for y in 0..6 {
+pub fn hard_drop_only<const W: usize>(mut board: BitBoard<W>, placement: Placement) -> bool {
+    for y in 0..BitBoard::<W>::HEIGHT {
         if board.line_filled(y) {
-            board.0 &= !((1 << 10) - 1 << 10 * y);
+            board.0 &= !(BitBoard::<W>::ROW << (W * y));
         }
     }
     !board.overlaps(placement.harddrop_mask())
 }
 
-pub fn tucks(board: BitBoard, placement: Placement) -> bool {
-    for x in placement.x..=10 - placement.kind.width() {
+pub fn tucks<const W: usize>(board: BitBoard<W>, placement: Placement) -> bool {
+    for x in placement.x..=W as u8 - placement.kind.width() {
         let placement = Placement { x, ..placement };
         if board.overlaps(placement.board()) {
             break;
@@ -32,7 +34,328 @@ pub fn tucks(board: BitBoard, placement: Placement) -> bool {
             return true;
         }
     }
-    return false;
+    false
+}
+
+/// Searches every position reachable from spawn using shifts, soft drop and SRS rotations, and
+/// accepts the placement if any of its final positions is among them.
+pub fn srs<const W: usize>(board: BitBoard<W>, placement: Placement) -> bool {
+    movement_search(&Srs, &Field::default(), board, placement)
+}
+
+/// Like `srs`, but pieces spawn and rotate according to the given rotation system.
+pub fn movement<const W: usize>(
+    rotation_system: impl RotationSystem + Clone,
+) -> impl Fn(BitBoard<W>, Placement) -> bool + Clone {
+    movement_in(rotation_system, Field::default())
+}
+
+/// Like `movement`, but pieces spawn and move in the given playfield instead of an empty one.
+pub fn movement_in<const W: usize>(
+    rotation_system: impl RotationSystem + Clone,
+    field: Field<W>,
+) -> impl Fn(BitBoard<W>, Placement) -> bool + Clone {
+    move |board, placement| movement_search(&rotation_system, &field, board, placement)
+}
+
+/// The playfield around the rows the PC is solved in. All rows are counted from the bottom of the
+/// board, before any lines are cleared.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Field<const W: usize = 10> {
+    /// Position new pieces spawn at. The rotation system's spawn position is relative to this.
+    pub spawn_x: i32,
+    pub spawn_y: i32,
+    /// Number of rows pieces can move in, including any hidden rows above the visible field.
+    pub height: i32,
+    /// Number of rows the PC is solved in. The stack starts above them.
+    pub pc_height: i32,
+    /// Height of the stack in each column. Cells above the PC's rows and below this height are
+    /// filled, e.g. when solving a PC beside a tall stack or at the bottom of a well.
+    pub stack: [i32; W],
+}
+
+impl<const W: usize> Default for Field<W> {
+    /// Guideline spawn and field height, with pieces spawning in the middle of the board, or
+    /// just left of it on boards of an even width.
+    fn default() -> Self {
+        Field {
+            spawn_x: (W as i32 - 1) / 2,
+            spawn_y: 20,
+            height: 40,
+            pc_height: 4,
+            stack: [0; W],
+        }
+    }
+}
+
+/// Every placement of `piece` on `board` that the judge accepts, along with the final position
+/// of the piece.
+pub fn placements<const W: usize>(
+    board: BitBoard<W>,
+    piece: Piece,
+    placeability_judge: impl Fn(BitBoard<W>, Placement) -> bool,
+) -> Vec<(Placement, SrsPiece)> {
+    let height = BitBoard::<W>::HEIGHT;
+    let mut placements = vec![];
+    for states in &data::PIECE_STATES_BY_HEIGHT_KIND_CELLY[height - 1][piece as usize] {
+        for &kind in states.iter() {
+            for x in 0..=W as u8 - kind.width() {
+                let placement = Placement { kind, x };
+                if !placement.board().overlaps(board)
+                    && placement.supported_after_clears(board)
+                    && placeability_judge(board, placement)
+                {
+                    placements.push((placement, placement.srs_piece(board)[0]));
+                }
+            }
+        }
+    }
+    placements
+}
+
+/// Every placement of `piece` on `board` that can be reached from spawn by moving and rotating
+/// the piece, along with the final position it was reached in. This gives the same placements
+/// as `placements` with a `movement_in` judge, but only searches once.
+pub fn movement_placements<const W: usize>(
+    rotation_system: &impl RotationSystem,
+    field: &Field<W>,
+    board: BitBoard<W>,
+    piece: Piece,
+) -> Vec<(Placement, SrsPiece)> {
+    let playfield = Playfield::new(board, field);
+    let mut found = HashSet::new();
+    let mut placements = vec![];
+    search(rotation_system, &playfield, piece, |position, _| {
+        if playfield.shifted(position, 0, -1).is_none() {
+            if let Some(placement) = position.placement(board) {
+                // pieces resting on the stack beside the board aren't part of the PC
+                if placement.supported_after_clears(board) && found.insert(placement) {
+                    placements.push((placement, position));
+                }
+            }
+        }
+        false
+    });
+    placements
+}
+
+fn movement_search<const W: usize>(
+    rotation_system: &impl RotationSystem,
+    field: &Field<W>,
+    board: BitBoard<W>,
+    placement: Placement,
+) -> bool {
+    let playfield = Playfield::new(board, field);
+    let spawn = playfield.spawn(rotation_system, placement.kind.piece());
+    if playfield.open_above_board(spawn) && tucks(board, placement) {
+        return true;
+    }
+
+    let targets = placement.srs_piece(board);
+    search(
+        rotation_system,
+        &playfield,
+        placement.kind.piece(),
+        |position, _| targets.contains(&position),
+    )
+}
+
+/// Visits every position reachable from spawn until `found` returns true. `found` is called
+/// every time a position is reached, along with the rotation state the piece was rotated from
+/// and the index of the kick used if the piece was rotated into that position.
+pub(crate) fn search<const W: usize>(
+    rotation_system: &impl RotationSystem,
+    playfield: &Playfield<W>,
+    piece: Piece,
+    mut found: impl FnMut(SrsPiece, Option<(Rotation, usize)>) -> bool,
+) -> bool {
+    let spawn = playfield.spawn(rotation_system, piece);
+    if !playfield.fits(spawn) {
+        return false;
+    }
+    let search_top = playfield.search_top(spawn);
+    let spawn = SrsPiece {
+        y: spawn.y.min(search_top),
+        ..spawn
+    };
+    if found(spawn, None) {
+        return true;
+    }
+
+    // bitmask of x positions visited, indexed by y and rotation
+    let mut visited = vec![[0u16; 4]; search_top as usize + 1];
+    let mut stack = vec![spawn];
+    visited[spawn.y as usize][spawn.rotation as usize] |= 1 << spawn.x;
+
+    while let Some(piece) = stack.pop() {
+        let shift = |dx, dy| playfield.shifted(piece, dx, dy).map(|p| (p, None));
+        let rotate = |to| {
+            playfield
+                .kicked(rotation_system, piece, to)
+                .map(|(p, kick)| (p, Some((piece.rotation, kick))))
+        };
+        let moves = [
+            shift(-1, 0),
+            shift(1, 0),
+            shift(0, -1),
+            rotate(piece.rotation.cw()),
+            rotate(piece.rotation.ccw()),
+            if rotation_system.has_180() {
+                rotate(piece.rotation.opposite())
+            } else {
+                None
+            },
+        ];
+        for &(next, kick) in moves.iter().flatten() {
+            if next.y > search_top {
+                continue;
+            }
+            if found(next, kick) {
+                return true;
+            }
+            let seen = &mut visited[next.y as usize][next.rotation as usize];
+            if *seen & 1 << next.x == 0 {
+                *seen |= 1 << next.x;
+                stack.push(next);
+            }
+        }
+    }
+
+    false
+}
+
+/// The space a piece moves through, in the coordinates of the board after line clears.
+pub(crate) struct Playfield<const W: usize = 10> {
+    board: BitBoard<W>,
+    /// First row above the PC's rows
+    board_top: i32,
+    /// Rows above the PC's rows are filled below these heights
+    stack: [i32; W],
+    /// First row above the stack and the board
+    stack_top: i32,
+    height: i32,
+    spawn_x: i32,
+    spawn_y: i32,
+}
+
+impl<const W: usize> Playfield<W> {
+    pub(crate) fn new(board: BitBoard<W>, field: &Field<W>) -> Self {
+        let cleared = (0..MAX_HEIGHT).filter(|&y| board.line_filled(y)).count() as i32;
+        let board_top = field.pc_height - cleared;
+        let mut stack = field.stack;
+        for h in &mut stack {
+            *h -= cleared;
+        }
+        let board = board.lines_cleared();
+        // cells of the board above the PC's rows are still in the way
+        let highest = (0..MAX_HEIGHT)
+            .filter(|&y| (board.0 >> (W * y)) & BitBoard::<W>::ROW != 0)
+            .map(|y| y as i32 + 1)
+            .fold(board_top, i32::max);
+        Playfield {
+            board,
+            board_top,
+            stack_top: stack.iter().copied().fold(highest, i32::max),
+            stack,
+            height: field.height,
+            spawn_x: field.spawn_x,
+            spawn_y: field.spawn_y,
+        }
+    }
+
+    /// Whether every column is empty from the top of the board to the ceiling, and the piece
+    /// spawns clear of the board with room to rotate, so it can get above any column in any
+    /// rotation state.
+    fn open_above_board(&self, spawn: SrsPiece) -> bool {
+        self.stack.iter().all(|&h| h <= self.board_top)
+            && spawn.y >= self.stack_top + 2
+            && self.height - self.stack_top >= 5
+            && self.fits(spawn)
+    }
+
+    pub(crate) fn spawn(&self, rotation_system: &impl RotationSystem, piece: Piece) -> SrsPiece {
+        let spawn = rotation_system.spawn(piece);
+        SrsPiece {
+            x: spawn.x + self.spawn_x,
+            y: spawn.y + self.spawn_y,
+            ..spawn
+        }
+    }
+
+    /// Highest row the center of the piece needs to visit. Two rows above the stack, no piece
+    /// can touch it in any rotation state, so if there is room to rotate between there and the
+    /// ceiling, everything above is open air that is reachable from spawn. That means we can
+    /// start the search there instead.
+    pub(crate) fn search_top(&self, spawn: SrsPiece) -> i32 {
+        let open_air = self.stack_top + 2;
+        if spawn.y >= open_air && self.height - self.stack_top >= 5 {
+            open_air
+        } else {
+            self.height
+        }
+    }
+
+    pub(crate) fn fits(&self, piece: SrsPiece) -> bool {
+        piece.cells().iter().all(|&(x, y)| !self.occupied(x, y))
+    }
+
+    /// Whether a cell is filled or outside of the playfield
+    pub(crate) fn occupied(&self, x: i32, y: i32) -> bool {
+        !(0..W as i32).contains(&x)
+            || y < 0
+            || y >= self.height
+            || y >= self.board_top && y < self.stack[x as usize]
+            || self.board.cell_filled(x as usize, y as usize)
+    }
+
+    pub(crate) fn shifted(&self, piece: SrsPiece, dx: i32, dy: i32) -> Option<SrsPiece> {
+        let piece = SrsPiece {
+            x: piece.x + dx,
+            y: piece.y + dy,
+            ..piece
+        };
+        if self.fits(piece) {
+            Some(piece)
+        } else {
+            None
+        }
+    }
+
+    /// Moves the piece down as far as it can go.
+    pub(crate) fn dropped(&self, mut piece: SrsPiece) -> SrsPiece {
+        while let Some(lower) = self.shifted(piece, 0, -1) {
+            piece = lower;
+        }
+        piece
+    }
+
+    pub(crate) fn rotated(
+        &self,
+        rotation_system: &impl RotationSystem,
+        piece: SrsPiece,
+        to: Rotation,
+    ) -> Option<SrsPiece> {
+        self.kicked(rotation_system, piece, to)
+            .map(|(piece, _)| piece)
+    }
+
+    /// Rotates the piece, returning its new position and the index of the kick that was used.
+    pub(crate) fn kicked(
+        &self,
+        rotation_system: &impl RotationSystem,
+        piece: SrsPiece,
+        to: Rotation,
+    ) -> Option<(SrsPiece, usize)> {
+        let rotated = SrsPiece {
+            rotation: to,
+            ..piece
+        };
+        rotation_system
+            .kicks(piece.piece, piece.rotation, to)
+            .iter()
+            .enumerate()
+            .find_map(|(i, &(dx, dy))| self.shifted(rotated, dx, dy).map(|p| (p, i)))
+    }
 }
 
 pub fn simple_srs_spins(board: BitBoard, placement: Placement) -> bool {
@@ -45,10 +368,10 @@ pub fn simple_srs_spins(board: BitBoard, placement: Placement) -> bool {
     let x = placement.x as usize;
     let y = piece.y as usize;
 
-    let check_empty = |mask: u64| board.0 & mask << 10 * y + x == 0;
+    let check_empty = |mask: u128| board.0 & mask << (10 * y + x) == 0;
     // vertical offset so we can check for empty cells below the placement
     let check_empty_v =
-        |mask: u64, off: usize| y >= off && board.0 & mask << 10 * (y - off) + x == 0;
+        |mask: u128, off: usize| y >= off && board.0 & mask << (10 * (y - off) + x) == 0;
 
     // this is a visible description of all the spins we're detecting:
     // https://fumen.zui.jp/?v115@pgxhHexhIewhReA8cevEn9gwhIexhlenpfpgQaAewh?GeQaAewhGeRawhGeRaAeA8FeAAceflf+gwhIexhkenpuEBU?9UTASIB5DjB98AQWrrDTG98AXO98AwyjXEroo2AseirDFbE?cEoe0TAyE88AQzgeEFbMwDv3STASorJEvwh1DIhRaAAGeA8?beaquAAIhxhkeyufIhRaGeA8AAAeA8ZeaqfIhxhkeyuf+gR?aHeQ4QaGeAABeAAZealf+gxhIewhkeipf+gRaGeA8AAQaA8?jealf+gxhIewhkeipf/gQaHewhQakeelf/gwhIewhkempfH?hAAAeQaAAFeA8BeA8ZedqfJhwhIewhae1ufIhQaJeQaaetp?fIhwhIewhbeVvfIhQaHeAAQaAeAAZetpfIhwhlelpfIhQaJ?ewhae9pfpgwhAeQaGewhAeQaGewhAeQaGewhAeQaIeQaae9?pfIhwhlelpfIhQaHewhcetpfHhQaIeQace6pfHhxhSewhRe?ypfHhRaSeQaRe6pfHhxhSewhReipfpgQaAewhGeQaAewhGe?QaAewhGeRawhIewhHeQaReqpfIhwhJeQaHexhQeipfIhQaJ?ewhHeRaQeqpfIhwhQaIeQaHexhQeypfrgQaIeQaHeQpQaHe?QpIeAtIeQpQaQeAAe+gwSIewSHeAtAeBtGewSReAAeqgQaw?hHeQawhHeQaAtHeQaAtGeBPAeAPGeQaAtQeAAe/gwSIewSG?eBtAeAtHewSQeAAe
@@ -166,3 +489,321 @@ pub fn simple_srs_spins(board: BitBoard, placement: Placement) -> bool {
         _ => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The placement of `piece` that fills the cells marked in `cells`.
+    fn placement<const W: usize>(board: BitBoard<W>, piece: Piece, cells: &str) -> Placement {
+        let cells: BitBoard<W> = cells.parse().unwrap();
+        placements(board, piece, always)
+            .into_iter()
+            .map(|(placement, _)| placement)
+            .find(|placement| placement.board() == cells)
+            .unwrap()
+    }
+
+    #[test]
+    fn srs_finds_t_spins() {
+        let board: BitBoard = "
+            XXX_______
+            XX___XXXXX
+            XXX_XXXXXX
+        "
+        .parse()
+        .unwrap();
+        let tsd = placement(
+            board,
+            Piece::T,
+            "
+            __________
+            __XXX_____
+            ___X______
+            ",
+        );
+        assert!(!tucks(board, tsd));
+        assert!(simple_srs_spins(board, tsd));
+        assert!(srs(board, tsd));
+    }
+
+    #[test]
+    fn srs_finds_tucks() {
+        let board: BitBoard = "
+            ________XX
+            __________
+            XXXXX_____
+        "
+        .parse()
+        .unwrap();
+        let tucked = placement(
+            board,
+            Piece::S,
+            "
+            __________
+            _______XX_
+            ______XX__
+            ",
+        );
+        assert!(!hard_drop_only(board, tucked));
+        assert!(tucks(board, tucked));
+        assert!(srs(board, tucked));
+    }
+
+    #[test]
+    fn srs_rejects_covered_placements() {
+        let board: BitBoard = "
+            XXX_______
+            X___XXXXXX
+            X___XXXXXX
+        "
+        .parse()
+        .unwrap();
+        let covered = placement(
+            board,
+            Piece::O,
+            "
+            __________
+            _XX_______
+            _XX_______
+            ",
+        );
+        assert!(!tucks(board, covered));
+        assert!(!srs(board, covered));
+        // the column beside it is open though
+        let i = placement(
+            board,
+            Piece::I,
+            "
+            ___X______
+            ___X______
+            ___X______
+            ___X______
+            ",
+        );
+        assert!(srs(board, i));
+    }
+
+    #[test]
+    fn spawning_low_rules_out_tucks() {
+        let board: BitBoard = "
+            ____X_____
+            ____X_____
+            ____X_____
+            ____X_____
+        "
+        .parse()
+        .unwrap();
+        let right = placement(
+            board,
+            Piece::O,
+            "
+            __________
+            __________
+            ______XX__
+            ______XX__
+            ",
+        );
+        let left = placement(
+            board,
+            Piece::O,
+            "
+            __________
+            __________
+            _XX_______
+            _XX_______
+            ",
+        );
+        assert!(srs(board, right));
+
+        // the piece spawns left of the wall, and can't get over it
+        let field = Field {
+            spawn_x: 1,
+            spawn_y: 1,
+            ..Field::default()
+        };
+        assert!(!movement_in(Srs, field)(board, right));
+        assert!(movement_in(Srs, field)(board, left));
+    }
+
+    #[test]
+    fn stack_beside_a_well_blocks_wide_pieces() {
+        let board: BitBoard = "
+            XXXXXX____
+            XXXXXX____
+            XXXXXX____
+            XXXXXX____
+        "
+        .parse()
+        .unwrap();
+        let o = placement(
+            board,
+            Piece::O,
+            "
+            __________
+            __________
+            ______XX__
+            ______XX__
+            ",
+        );
+        let i = placement(
+            board,
+            Piece::I,
+            "
+            _________X
+            _________X
+            _________X
+            _________X
+            ",
+        );
+        assert!(srs(board, o));
+        assert!(srs(board, i));
+
+        // only a vertical I fits down the column left open beside the stack
+        let field = Field {
+            stack: [20, 20, 20, 20, 20, 20, 20, 20, 20, 0],
+            spawn_y: 21,
+            ..Field::default()
+        };
+        assert!(!movement_in(Srs, field)(board, o));
+        assert!(movement_in(Srs, field)(board, i));
+    }
+
+    #[test]
+    fn ceiling_limits_placements() {
+        let board: BitBoard = "
+            XXXX____XX
+            XXXX___XXX
+            XXXX__XXXX
+            XXXX___XXX
+        "
+        .parse()
+        .unwrap();
+        let well = placement(
+            board,
+            Piece::I,
+            "
+            ____X_____
+            ____X_____
+            ____X_____
+            ____X_____
+            ",
+        );
+        let above = placement(
+            board,
+            Piece::I,
+            "
+            X_________
+            X_________
+            X_________
+            X_________
+            __________
+            __________
+            __________
+            __________
+            ",
+        );
+        assert!(movement(Srs)(board, above));
+
+        // too low for the shortcut, which needs room to rotate above the board, but the search
+        // still finds the placements below the ceiling
+        let field = Field {
+            height: 7,
+            spawn_y: 5,
+            ..Field::default()
+        };
+        assert!(movement_in(Srs, field)(board, well));
+        assert!(!movement_in(Srs, field)(board, above));
+    }
+
+    #[test]
+    fn move_generator_matches_the_judge() {
+        fn check<const W: usize>(board: BitBoard<W>, field: Field<W>) {
+            for &piece in &PIECES {
+                let generated: HashSet<_> = movement_placements(&Srs, &field, board, piece)
+                    .into_iter()
+                    .map(|(placement, _)| placement)
+                    .collect();
+                let judged: HashSet<_> = placements(board, piece, movement_in(Srs, field))
+                    .into_iter()
+                    .map(|(placement, _)| placement)
+                    .collect();
+                assert!(!judged.is_empty());
+                assert_eq!(generated, judged, "{:?} on\n{}", piece, board);
+            }
+        }
+        let tsd: BitBoard = "
+            XXX_______
+            XX___XXXXX
+            XXX_XXXXXX
+        "
+        .parse()
+        .unwrap();
+        check(tsd, Field::default());
+        check(
+            tsd,
+            Field {
+                stack: [8, 8, 8, 0, 0, 0, 0, 0, 8, 8],
+                pc_height: 3,
+                ..Field::default()
+            },
+        );
+        let pco: BitBoard<10> = "
+            XXXX____XX
+            XXXX___XXX
+            XXXX__XXXX
+            XXXX___XXX
+        "
+        .parse()
+        .unwrap();
+        check(pco, Field::default());
+        check(BitBoard::<6>(0), Field::default());
+    }
+
+    #[test]
+    fn moves_pieces_on_other_widths() {
+        let board: BitBoard<6> = "
+            XX____
+            ______
+            ______
+        "
+        .parse()
+        .unwrap();
+        let tucked = placement(
+            board,
+            Piece::O,
+            "
+            ______
+            XX____
+            XX____
+            ",
+        );
+        assert!(!hard_drop_only(board, tucked));
+        assert!(srs(board, tucked));
+        // a stack beside the PC's rows rules out the shortcut for tucks
+        let field = Field {
+            pc_height: 3,
+            stack: [10, 10, 0, 0, 0, 0],
+            ..Field::default()
+        };
+        assert!(movement_in(Srs, field)(board, tucked));
+
+        let board: BitBoard<6> = "
+            XXXXX_
+            ______
+            ______
+        "
+        .parse()
+        .unwrap();
+        let covered = placement(
+            board,
+            Piece::O,
+            "
+            ______
+            XX____
+            XX____
+            ",
+        );
+        assert!(!srs(board, covered));
+    }
+}
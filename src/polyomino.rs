@@ -0,0 +1,729 @@
+//! Perfect clears with custom piece sets, such as pentominoes, trominoes or the pieces of a
+//! modded game.
+//!
+//! The rest of the crate is built around the seven tetrominoes, with tables generated at build
+//! time. Here the tables are built at runtime from the shapes the caller gives, and the same
+//! combination and permutation searches run on them, so any set of polyominoes can be used at
+//! the cost of some speed. Pieces are referred to by their index in the list the `PieceTable`
+//! was built from. Rotation systems only exist for tetrominoes, so placements can be judged by
+//! hard drops but not by movement.
+
+use crate::combination::{find_combos_mt, Combinations, Cursor, PieceKinds};
+use crate::solve::{gcd, pc_heights, solve_placement_combo, PieceSequence};
+use crate::{BitBoard, NoPruning};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A piece shape, given as the cells it covers in one orientation. The other orientations are
+/// found by rotating it, so mirror images are different pieces.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Polyomino {
+    cells: Vec<(i32, i32)>,
+}
+
+impl Polyomino {
+    /// Fails if there are no cells, a cell is given twice, or the cells span more than 16
+    /// rows or columns.
+    pub fn new(cells: &[(i32, i32)]) -> Result<Self, PolyominoError> {
+        let span = |coord: fn(&(i32, i32)) -> i32| {
+            let min = cells.iter().map(coord).min().unwrap_or(0) as i64;
+            let max = cells.iter().map(coord).max().unwrap_or(0) as i64;
+            max - min + 1
+        };
+        let duplicate = cells
+            .iter()
+            .enumerate()
+            .any(|(i, cell)| cells[..i].contains(cell));
+        if cells.is_empty() || duplicate || span(|c| c.0) > 16 || span(|c| c.1) > 16 {
+            return Err(PolyominoError::InvalidShape);
+        }
+        Ok(Polyomino {
+            cells: cells.to_vec(),
+        })
+    }
+
+    pub fn cells(&self) -> &[(i32, i32)] {
+        &self.cells
+    }
+
+    /// Number of cells the piece covers.
+    pub fn size(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// The distinct orientations of the piece, each as the cells of its rows from the bottom up,
+    /// with the lowest row and leftmost column at 0.
+    fn orientations(&self) -> Vec<Vec<u16>> {
+        let mut orientations: Vec<Vec<u16>> = vec![];
+        let mut cells = self.cells.clone();
+        for _ in 0..4 {
+            let left = cells.iter().map(|&(x, _)| x).min().unwrap_or(0);
+            let bottom = cells.iter().map(|&(_, y)| y).min().unwrap_or(0);
+            let height = cells
+                .iter()
+                .map(|&(_, y)| y - bottom + 1)
+                .max()
+                .unwrap_or(0);
+            let mut rows = vec![0; height as usize];
+            for &(x, y) in &cells {
+                rows[(y - bottom) as usize] |= 1 << (x - left);
+            }
+            if !orientations.contains(&rows) {
+                orientations.push(rows);
+            }
+            for cell in &mut cells {
+                *cell = (cell.1, -cell.0);
+            }
+        }
+        orientations
+    }
+}
+
+/// Error returned when pieces or queues of polyominoes can't be used.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum PolyominoError {
+    /// The cells of a piece are empty, repeated, or span more than 16 rows or columns.
+    InvalidShape,
+    /// A piece table was built from no pieces.
+    NoPieces,
+    /// A piece doesn't fit on the board in any orientation.
+    DoesNotFit,
+    /// A queue or piece count refers to a piece that isn't in the table.
+    UnknownPiece,
+}
+
+impl std::fmt::Display for PolyominoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PolyominoError::InvalidShape => write!(f, "invalid piece shape"),
+            PolyominoError::NoPieces => write!(f, "no pieces"),
+            PolyominoError::DoesNotFit => write!(f, "piece doesn't fit on the board"),
+            PolyominoError::UnknownPiece => write!(f, "piece not in the table"),
+        }
+    }
+}
+
+impl std::error::Error for PolyominoError {}
+
+/// The ways each piece can be placed on a board of width `W`, built from a list of pieces.
+#[derive(Clone, Debug)]
+pub struct PieceTable<const W: usize = 10> {
+    pieces: Vec<Polyomino>,
+    /// `states[height - 1][piece][y]` are the states of the piece within the lowest `height`
+    /// rows whose lowest cell in the leftmost column is in row `y`, positioned against the left
+    /// wall and sorted by width.
+    states: Vec<Vec<Vec<Vec<State>>>>,
+    /// The greatest common divisor of the piece sizes, which the number of cells to fill must
+    /// be a multiple of.
+    cell_gcd: usize,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct State {
+    bits: u128,
+    width: usize,
+    /// Number of rows from the bottom of the board to the top of the piece.
+    top: usize,
+    hurdles: u128,
+    below: u128,
+    harddrop: u128,
+    on_floor: bool,
+}
+
+impl<const W: usize> PieceTable<W> {
+    /// Builds the tables for the pieces. Orientations that don't fit on the board are left out,
+    /// and it fails if a piece has none that do.
+    pub fn new(pieces: &[Polyomino]) -> Result<Self, PolyominoError> {
+        if pieces.is_empty() {
+            return Err(PolyominoError::NoPieces);
+        }
+        let mut all_states = vec![];
+        for piece in pieces {
+            let mut by_y = vec![vec![]; BitBoard::<W>::HEIGHT];
+            for rows in piece.orientations() {
+                let width = rows.iter().map(|&r| 16 - r.leading_zeros() as usize).max();
+                match width {
+                    Some(width) if width <= W && rows.len() <= BitBoard::<W>::HEIGHT => {
+                        gen_states::<W>(&rows, width, &mut vec![], &mut by_y)
+                    }
+                    _ => {}
+                }
+            }
+            if by_y.iter().all(Vec::is_empty) {
+                return Err(PolyominoError::DoesNotFit);
+            }
+            all_states.push(by_y);
+        }
+
+        let states = (1..=BitBoard::<W>::HEIGHT)
+            .map(|height| {
+                all_states
+                    .iter()
+                    .map(|by_y| {
+                        by_y.iter()
+                            .map(|states| {
+                                let mut states: Vec<State> = states
+                                    .iter()
+                                    .copied()
+                                    .filter(|state| state.top <= height)
+                                    .collect();
+                                states.sort_by_key(|state| state.width);
+                                states
+                            })
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(PieceTable {
+            pieces: pieces.to_vec(),
+            states,
+            cell_gcd: pieces.iter().map(Polyomino::size).fold(0, gcd),
+        })
+    }
+
+    pub fn pieces(&self) -> &[Polyomino] {
+        &self.pieces
+    }
+
+    /// Pads the counts with zeroes to one for each piece.
+    fn counts(&self, counts: &[u8]) -> Result<Vec<u8>, PolyominoError> {
+        if counts.len() > self.pieces.len() {
+            return Err(PolyominoError::UnknownPiece);
+        }
+        let mut counts = counts.to_vec();
+        counts.resize(self.pieces.len(), 0);
+        Ok(counts)
+    }
+
+    /// The PC heights to try for the queue, lowest first, along with the queue's piece counts.
+    /// Only the first 32 pieces of the queue are used.
+    fn queue_heights(
+        &self,
+        queue: &[usize],
+        board: BitBoard<W>,
+    ) -> Result<(Vec<usize>, Vec<u8>), PolyominoError> {
+        let queue = &queue[..queue.len().min(32)];
+        let mut counts = vec![0; self.pieces.len()];
+        for &piece in queue {
+            *counts.get_mut(piece).ok_or(PolyominoError::UnknownPiece)? += 1;
+        }
+        let available: usize = queue.iter().map(|&p| self.pieces[p].size()).sum();
+        let filled = board.0.count_ones() as usize;
+        let heights = pc_heights(board, self.cell_gcd)
+            .into_iter()
+            .map(|(height, _)| height)
+            .take_while(|&height| W * height - filled <= available)
+            .collect();
+        Ok((heights, counts))
+    }
+}
+
+/// Adds the states of an orientation for every choice of the board rows its rows go in.
+fn gen_states<const W: usize>(
+    rows: &[u16],
+    width: usize,
+    board_rows: &mut Vec<usize>,
+    by_y: &mut [Vec<State>],
+) {
+    if board_rows.len() == rows.len() {
+        let mut state = State {
+            bits: 0,
+            width,
+            top: board_rows[rows.len() - 1] + 1,
+            hurdles: 0,
+            below: 0,
+            harddrop: 0,
+            on_floor: board_rows[0] == 0,
+        };
+        let mut hdrow = 0;
+        for (i, (&row, &y)) in rows.iter().zip(board_rows.iter()).enumerate() {
+            let row = row as u128;
+            state.bits |= row << (W * y);
+            hdrow |= row;
+            state.harddrop |= hdrow << (W * y);
+            // the cells below are those of the previous row, as if the crossed lines were cleared
+            match i.checked_sub(1) {
+                Some(prev) => state.below |= row << (W * board_rows[prev]),
+                None if y != 0 => state.below |= row << (W * (y - 1)),
+                None => {}
+            }
+            if let Some(&next) = board_rows.get(i + 1) {
+                for hurdle in y + 1..next {
+                    state.hurdles |= BitBoard::<W>::ROW << (W * hurdle);
+                }
+            }
+        }
+        for y in state.top..BitBoard::<W>::HEIGHT {
+            state.harddrop |= hdrow << (W * y);
+        }
+        let anchor = rows.iter().position(|&r| r & 1 != 0).unwrap();
+        by_y[board_rows[anchor]].push(state);
+        return;
+    }
+    let lowest = board_rows.last().map_or(0, |&y| y + 1);
+    let highest = BitBoard::<W>::HEIGHT - (rows.len() - board_rows.len());
+    for y in lowest..=highest {
+        board_rows.push(y);
+        gen_states::<W>(rows, width, board_rows, by_y);
+        board_rows.pop();
+    }
+}
+
+/// A piece placed on the board. Like the tetromino placements of the rest of the crate, it can
+/// cross lines that must be filled, and cleared, before it can be placed.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Placement<const W: usize = 10> {
+    /// Index of the piece in the list the table was built from.
+    pub piece: usize,
+    /// The cells the piece covers, before any lines are cleared.
+    pub board: BitBoard<W>,
+    hurdles: BitBoard<W>,
+    below: BitBoard<W>,
+    harddrop: BitBoard<W>,
+    on_floor: bool,
+}
+
+impl<const W: usize> Placement<W> {
+    fn new(piece: usize, state: &State, x: usize) -> Self {
+        Placement {
+            piece,
+            board: BitBoard(state.bits << x),
+            hurdles: BitBoard(state.hurdles),
+            below: BitBoard(state.below << x),
+            harddrop: BitBoard(state.harddrop << x),
+            on_floor: state.on_floor,
+        }
+    }
+
+    /// Whether the lines the piece crosses are filled and, once they are cleared, the piece
+    /// rests on the floor or on a filled cell.
+    pub fn supported_after_clears(&self, mut on: BitBoard<W>) -> bool {
+        if self.hurdles.remove(on) != BitBoard(0) {
+            return false;
+        }
+        // copy lines below filled lines into filled lines
+        for y in 1..BitBoard::<W>::HEIGHT {
+            if on.line_filled(y) {
+                on.0 &= (on.0 << W) | !(BitBoard::<W>::ROW << (W * y));
+            }
+        }
+        self.on_floor || on.overlaps(self.below)
+    }
+
+    fn supported_without_clears(&self, on: BitBoard<W>) -> bool {
+        self.hurdles.remove(on) == BitBoard(0) && (self.on_floor || on.overlaps(self.below))
+    }
+
+    /// The cells that must be empty, once filled lines are cleared, for the piece to be hard
+    /// dropped into place.
+    pub fn harddrop_mask(&self) -> BitBoard<W> {
+        self.harddrop
+    }
+}
+
+pub fn always<const W: usize>(_: BitBoard<W>, _: Placement<W>) -> bool {
+    true
+}
+
+pub fn hard_drop_only<const W: usize>(mut board: BitBoard<W>, placement: Placement<W>) -> bool {
+    for y in 0..BitBoard::<W>::HEIGHT {
+        if board.line_filled(y) {
+            board.0 &= !(BitBoard::<W>::ROW << (W * y));
+        }
+    }
+    !board.overlaps(placement.harddrop_mask())
+}
+
+impl<const W: usize> PieceKinds<W> for &PieceTable<W> {
+    type Piece = usize;
+    type Placement = Placement<W>;
+    type Set = Vec<u8>;
+
+    fn piece(self, placement: Placement<W>) -> usize {
+        placement.piece
+    }
+
+    fn board(self, placement: Placement<W>) -> BitBoard<W> {
+        placement.board
+    }
+
+    fn supported_after_clears(self, placement: Placement<W>, on: BitBoard<W>) -> bool {
+        placement.supported_after_clears(on)
+    }
+
+    fn supported_without_clears(self, placement: Placement<W>, on: BitBoard<W>) -> bool {
+        placement.supported_without_clears(on)
+    }
+
+    fn without(self, counts: &Vec<u8>, piece: usize) -> Vec<u8> {
+        let mut counts = counts.clone();
+        counts[piece] -= 1;
+        counts
+    }
+
+    fn next_placement(
+        self,
+        counts: &Vec<u8>,
+        board: BitBoard<W>,
+        height: usize,
+        x: usize,
+        y: usize,
+        cursor: &mut Cursor,
+    ) -> Option<Placement<W>> {
+        while let Some(&count) = counts.get(cursor.piece) {
+            let states: &[State] = if count != 0 {
+                &self.states[height - 1][cursor.piece][y]
+            } else {
+                &[]
+            };
+            while let Some(state) = states.get(cursor.state) {
+                cursor.state += 1;
+                if x + state.width > W {
+                    // sorted by width, so the rest don't fit either
+                    break;
+                }
+                let placement = Placement::new(cursor.piece, state, x);
+                if !placement.board.overlaps(board) {
+                    return Some(placement);
+                }
+            }
+            cursor.piece += 1;
+            cursor.state = 0;
+        }
+        None
+    }
+
+    fn can_fill(self, _: BitBoard<W>, _: &Vec<u8>, _: usize) -> bool {
+        true
+    }
+}
+
+/// Finds the sets of placements that fill every empty cell in the lowest `height` rows, using at
+/// most `counts[piece]` of each piece. Fails if `counts` has more entries than the table has
+/// pieces.
+pub fn find_combinations<const W: usize>(
+    table: &PieceTable<W>,
+    counts: &[u8],
+    board: BitBoard<W>,
+    abort: &AtomicBool,
+    height: usize,
+    mut combo_consumer: impl FnMut(&[Placement<W>]),
+) -> Result<(), PolyominoError> {
+    let counts = table.counts(counts)?;
+    if height == 0 || height > BitBoard::<W>::HEIGHT {
+        return Ok(());
+    }
+    let mut combos = Combinations::new(table, counts, board, abort, height, NoPruning);
+    while let Some(combo) = combos.next_combination() {
+        combo_consumer(combo);
+    }
+    Ok(())
+}
+
+/// Like `find_combinations`, but searches on multiple threads.
+pub fn find_combinations_mt<const W: usize>(
+    table: &PieceTable<W>,
+    counts: &[u8],
+    board: BitBoard<W>,
+    abort: &AtomicBool,
+    height: usize,
+    combo_consumer: impl FnMut(&[Placement<W>]) + Clone + Send,
+) -> Result<(), PolyominoError> {
+    let counts = table.counts(counts)?;
+    if height == 0 || height > BitBoard::<W>::HEIGHT {
+        return Ok(());
+    }
+    rayon::scope(|scope| {
+        find_combos_mt(
+            scope,
+            table,
+            vec![],
+            board,
+            BitBoard::filled(height),
+            counts,
+            abort,
+            height,
+            0,
+            NoPruning,
+            combo_consumer,
+        )
+    });
+    Ok(())
+}
+
+/// Finds the perfect clears the queue can do on the board, like `crate::solve_pc`, with the
+/// queue given as indices into the table's pieces. Fails if the queue has a piece that isn't in
+/// the table.
+///
+/// Heights are tried from the lowest up to the tallest the board can hold, stopping at the first
+/// height with any solutions. A height is skipped if the number of cells to fill is not a
+/// multiple of the piece sizes' common divisor, and the search ends once the queue can't cover
+/// the cells to fill. Only the first 32 pieces of the queue are used.
+#[allow(clippy::too_many_arguments)]
+pub fn solve_pc<const W: usize>(
+    table: &PieceTable<W>,
+    queue: &[usize],
+    board: BitBoard<W>,
+    hold_allowed: bool,
+    unique: bool,
+    abort: &AtomicBool,
+    placeability_judge: impl Fn(BitBoard<W>, Placement<W>) -> bool,
+    mut pc_consumer: impl FnMut(&[Placement<W>]),
+) -> Result<(), PolyominoError> {
+    let (heights, counts) = table.queue_heights(queue, board)?;
+    let sequence: PieceSequence<usize> = queue.iter().copied().collect();
+    for height in heights {
+        let mut found = false;
+        let mut combos = Combinations::new(table, counts.clone(), board, abort, height, NoPruning);
+        while let Some(combo) = combos.next_combination() {
+            solve_placement_combo(
+                table,
+                sequence,
+                board,
+                combo,
+                hold_allowed,
+                unique,
+                &placeability_judge,
+                |soln| {
+                    found = true;
+                    pc_consumer(soln)
+                },
+            );
+        }
+        if found {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Like `solve_pc`, but searches on multiple threads.
+#[allow(clippy::too_many_arguments)]
+pub fn solve_pc_mt<const W: usize>(
+    table: &PieceTable<W>,
+    queue: &[usize],
+    board: BitBoard<W>,
+    hold_allowed: bool,
+    unique: bool,
+    abort: &AtomicBool,
+    placeability_judge: impl Fn(BitBoard<W>, Placement<W>) -> bool + Sync,
+    pc_consumer: impl FnMut(&[Placement<W>]) + Clone + Send,
+) -> Result<(), PolyominoError> {
+    let (heights, counts) = table.queue_heights(queue, board)?;
+    let sequence: PieceSequence<usize> = queue.iter().copied().collect();
+    let placeability_judge = &placeability_judge;
+    for height in heights {
+        let found = &AtomicBool::new(false);
+        let mut pc_consumer = pc_consumer.clone();
+        find_combinations_mt(table, &counts, board, abort, height, move |combo| {
+            solve_placement_combo(
+                table,
+                sequence,
+                board,
+                combo,
+                hold_allowed,
+                unique,
+                placeability_judge,
+                |soln| {
+                    found.store(true, Ordering::Release);
+                    pc_consumer(soln)
+                },
+            )
+        })?;
+        if found.load(Ordering::Acquire) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tetrominoes() -> PieceTable {
+        let shapes: Vec<_> = [
+            &[(0, 0), (1, 0), (1, 1), (2, 1)][..],
+            &[(0, 1), (1, 1), (1, 0), (2, 0)],
+            &[(0, 0), (1, 0), (2, 0), (0, 1)],
+            &[(0, 0), (1, 0), (2, 0), (2, 1)],
+            &[(0, 0), (1, 0), (2, 0), (1, 1)],
+            &[(0, 0), (1, 0), (0, 1), (1, 1)],
+            &[(0, 0), (1, 0), (2, 0), (3, 0)],
+        ]
+        .iter()
+        .map(|cells| Polyomino::new(cells).unwrap())
+        .collect();
+        PieceTable::new(&shapes).unwrap()
+    }
+
+    fn count<const W: usize>(
+        table: &PieceTable<W>,
+        queue: &[usize],
+        board: BitBoard<W>,
+        hold_allowed: bool,
+        unique: bool,
+        placeability_judge: impl Fn(BitBoard<W>, Placement<W>) -> bool,
+    ) -> usize {
+        let abort = AtomicBool::new(false);
+        let mut solutions = 0;
+        solve_pc(
+            table,
+            queue,
+            board,
+            hold_allowed,
+            unique,
+            &abort,
+            placeability_judge,
+            |_| solutions += 1,
+        )
+        .unwrap();
+        solutions
+    }
+
+    #[test]
+    fn pentomino_pcs() {
+        let i = Polyomino::new(&[(0, 0), (1, 0), (2, 0), (3, 0), (4, 0)]).unwrap();
+        let u = Polyomino::new(&[(0, 0), (1, 0), (2, 0), (0, 1), (2, 1)]).unwrap();
+        let p = Polyomino::new(&[(0, 0), (1, 0), (0, 1), (1, 1), (0, 2)]).unwrap();
+        let table = PieceTable::<10>::new(&[i, u, p]).unwrap();
+        assert_eq!(table.pieces()[1].size(), 5);
+
+        // two I pieces already clear a line
+        assert_eq!(
+            count(&table, &[0; 4], BitBoard(0), false, false, hard_drop_only),
+            2
+        );
+        assert_eq!(
+            count(&table, &[0; 4], BitBoard(0), false, true, hard_drop_only),
+            1
+        );
+
+        // two P pieces fill a 5 by 2 hole, and hard drops can only place them one way round
+        let board: BitBoard = "XXXXX_____\nXXXXX_____".parse().unwrap();
+        assert_eq!(
+            count(&table, &[2, 2], board, true, false, hard_drop_only),
+            1
+        );
+        assert_eq!(count(&table, &[2, 2], board, true, false, always), 2);
+
+        // mirror images are different pieces
+        let board: BitBoard = "XXXXXXXX_X\nXXXXXXXX__\nXXXXXXXX__".parse().unwrap();
+        assert_eq!(count(&table, &[2], board, false, false, always), 1);
+        let board: BitBoard = "XXXXXXXXX_\nXXXXXXXX__\nXXXXXXXX__".parse().unwrap();
+        assert_eq!(count(&table, &[2], board, false, false, always), 0);
+    }
+
+    #[test]
+    fn skips_heights_the_pieces_cant_fill() {
+        let i = Polyomino::new(&[(0, 0), (1, 0), (2, 0)]).unwrap();
+        let table = PieceTable::<4>::new(&[i]).unwrap();
+        // 4 columns of 3 cell pieces: 3 lines are the lowest PC, filled by standing all the
+        // pieces up or by standing one up beside 3 lying down
+        assert_eq!(
+            count(&table, &[0; 4], BitBoard(0), false, true, hard_drop_only),
+            3
+        );
+        assert_eq!(
+            count(&table, &[0; 3], BitBoard(0), false, true, hard_drop_only),
+            0
+        );
+    }
+
+    #[test]
+    fn matches_the_tetromino_solver() {
+        let table = tetrominoes();
+        let abort = AtomicBool::new(false);
+        let pco: BitBoard = "XXXX____XX\nXXXX___XXX\nXXXX__XXXX\nXXXX___XXX"
+            .parse()
+            .unwrap();
+        for (board, queue) in [
+            (pco, vec![6, 4, 5, 2]),
+            (pco, vec![4, 6, 2, 5, 3]),
+            (BitBoard(0), vec![6, 3, 0, 2, 5, 5]),
+        ] {
+            let pieces: Vec<_> = queue.iter().map(|&p| crate::PIECES[p]).collect();
+            let mut expected = 0;
+            crate::solve_pc(
+                &pieces,
+                board,
+                true,
+                false,
+                &abort,
+                crate::placeability::hard_drop_only,
+                |_| expected += 1,
+            );
+            assert_ne!(expected, 0);
+            assert_eq!(
+                count(&table, &queue, board, true, false, hard_drop_only),
+                expected
+            );
+
+            let solutions = std::sync::Mutex::new(0);
+            solve_pc_mt(
+                &table,
+                &queue,
+                board,
+                true,
+                false,
+                &abort,
+                hard_drop_only,
+                |_| *solutions.lock().unwrap() += 1,
+            )
+            .unwrap();
+            assert_eq!(solutions.into_inner().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_pieces_and_queues() {
+        assert_eq!(Polyomino::new(&[]), Err(PolyominoError::InvalidShape));
+        assert_eq!(
+            Polyomino::new(&[(0, 0), (1, 0), (0, 0)]),
+            Err(PolyominoError::InvalidShape)
+        );
+        assert_eq!(
+            Polyomino::new(&[(0, 0), (16, 0)]),
+            Err(PolyominoError::InvalidShape)
+        );
+        assert_eq!(
+            Polyomino::new(&[(i32::MIN, 0), (i32::MAX, 0)]),
+            Err(PolyominoError::InvalidShape)
+        );
+
+        assert_eq!(
+            PieceTable::<10>::new(&[]).unwrap_err(),
+            PolyominoError::NoPieces
+        );
+        let long = Polyomino::new(&(0..13).map(|x| (x, 0)).collect::<Vec<_>>()).unwrap();
+        assert_eq!(
+            PieceTable::<10>::new(&[long]).unwrap_err(),
+            PolyominoError::DoesNotFit
+        );
+
+        let table = tetrominoes();
+        let abort = AtomicBool::new(false);
+        assert_eq!(
+            solve_pc(
+                &table,
+                &[7],
+                BitBoard(0),
+                true,
+                false,
+                &abort,
+                always,
+                |_| {}
+            ),
+            Err(PolyominoError::UnknownPiece)
+        );
+        assert_eq!(
+            find_combinations(&table, &[1; 8], BitBoard(0), &abort, 2, |_| {}),
+            Err(PolyominoError::UnknownPiece)
+        );
+        // only the first 32 pieces are used, so more than 255 of a piece doesn't overflow
+        assert_eq!(
+            count(&table, &[6; 300], BitBoard(0), true, true, hard_drop_only),
+            count(&table, &[6; 32], BitBoard(0), true, true, hard_drop_only)
+        );
+    }
+}
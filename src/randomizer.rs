@@ -0,0 +1,286 @@
+//! Models of piece randomizers, for weighting queues by how likely they are to occur.
+
+use crate::*;
+use std::collections::HashSet;
+use std::sync::atomic::AtomicBool;
+
+/// A randomizer state that can produce the next piece.
+pub trait Randomizer: Clone {
+    /// Every piece that can come next, with its probability and the state of the randomizer
+    /// after producing it.
+    fn next(&self) -> Vec<(Piece, f64, Self)>;
+
+    /// Returns the state after the randomizer has produced `pieces`, or `None` if it can't
+    /// produce them in that order.
+    fn after(&self, pieces: &[Piece]) -> Option<Self> {
+        let mut state = self.clone();
+        for &piece in pieces {
+            state = state.next().into_iter().find(|&(p, _, _)| p == piece)?.2;
+        }
+        Some(state)
+    }
+}
+
+/// Deals pieces from a shuffled bag of each of the 7 pieces, refilling it once it is empty.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SevenBag {
+    /// The pieces left in the current bag.
+    pub remaining: PieceSet,
+}
+
+/// Deals pieces from a shuffled bag of two of each of the 7 pieces, refilling it once it is
+/// empty.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct FourteenBag {
+    /// The pieces left in the current bag.
+    pub remaining: PieceSet,
+}
+
+/// Picks every piece uniformly at random.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Memoryless;
+
+/// The randomizer from the TGM series, which rolls a random piece up to `rolls` times until it
+/// gets one that is not in the history of the last 4 pieces.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct TgmHistory {
+    /// The last 4 pieces dealt, oldest first.
+    pub history: [Piece; 4],
+    /// How many times to roll before giving up and dealing a piece from the history. Must be at
+    /// least 1; `next` panics otherwise.
+    pub rolls: u32,
+}
+
+impl Default for SevenBag {
+    /// The state at the start of a bag.
+    fn default() -> Self {
+        SevenBag {
+            remaining: PieceSet([1; 7]),
+        }
+    }
+}
+
+impl Default for FourteenBag {
+    /// The state at the start of a bag.
+    fn default() -> Self {
+        FourteenBag {
+            remaining: PieceSet([2; 7]),
+        }
+    }
+}
+
+impl Default for TgmHistory {
+    /// The initial state of the TGM randomizer: a history of 4 Z pieces and 4 rolls.
+    fn default() -> Self {
+        TgmHistory {
+            history: [Piece::Z; 4],
+            rolls: 4,
+        }
+    }
+}
+
+impl Randomizer for SevenBag {
+    fn next(&self) -> Vec<(Piece, f64, Self)> {
+        bag_next(self.remaining, PieceSet([1; 7]))
+            .into_iter()
+            .map(|(p, prob, remaining)| (p, prob, SevenBag { remaining }))
+            .collect()
+    }
+}
+
+impl Randomizer for FourteenBag {
+    fn next(&self) -> Vec<(Piece, f64, Self)> {
+        bag_next(self.remaining, PieceSet([2; 7]))
+            .into_iter()
+            .map(|(p, prob, remaining)| (p, prob, FourteenBag { remaining }))
+            .collect()
+    }
+}
+
+impl Randomizer for Memoryless {
+    fn next(&self) -> Vec<(Piece, f64, Self)> {
+        PIECES.iter().map(|&p| (p, 1.0 / 7.0, Memoryless)).collect()
+    }
+}
+
+impl Randomizer for TgmHistory {
+    fn next(&self) -> Vec<(Piece, f64, Self)> {
+        assert!(self.rolls > 0, "the TGM randomizer needs at least one roll");
+        let in_history = |p| self.history.contains(&p);
+        let seen = PIECES.iter().filter(|&&p| in_history(p)).count() as f64 / 7.0;
+        PIECES
+            .iter()
+            .map(|&p| {
+                // a piece in the history only comes out if every roll hits the history, while
+                // any other piece comes out as soon as it is rolled
+                let prob = if in_history(p) {
+                    seen.powi(self.rolls as i32 - 1) / 7.0
+                } else {
+                    (0..self.rolls).map(|i| seen.powi(i as i32)).sum::<f64>() / 7.0
+                };
+                let mut next = *self;
+                next.history.rotate_left(1);
+                next.history[3] = p;
+                (p, prob, next)
+            })
+            .filter(|&(_, prob, _)| prob > 0.0)
+            .collect()
+    }
+}
+
+fn bag_next(remaining: PieceSet, full: PieceSet) -> Vec<(Piece, f64, PieceSet)> {
+    let remaining = if remaining == PieceSet::default() {
+        full
+    } else {
+        remaining
+    };
+    let total: u8 = remaining.0.iter().sum();
+    PIECES
+        .iter()
+        .filter(|&&p| remaining.contains(p))
+        .map(|&p| {
+            let prob = remaining.0[p as usize] as f64 / total as f64;
+            (p, prob, remaining.without(p))
+        })
+        .collect()
+}
+
+/// Every queue of `length` pieces the randomizer can produce next, with its probability.
+pub fn queues(randomizer: &impl Randomizer, length: usize) -> Vec<(Vec<Piece>, f64)> {
+    let mut result = vec![];
+    add_queues(randomizer, length, 1.0, &mut vec![], &mut result);
+    result
+}
+
+fn add_queues<R: Randomizer>(
+    randomizer: &R,
+    length: usize,
+    prob: f64,
+    queue: &mut Vec<Piece>,
+    into: &mut Vec<(Vec<Piece>, f64)>,
+) {
+    if queue.len() == length {
+        into.push((queue.clone(), prob));
+        return;
+    }
+    for (piece, p, next) in randomizer.next() {
+        queue.push(piece);
+        add_queues(&next, length, prob * p, queue, into);
+        queue.pop();
+    }
+}
+
+/// Probability that the board can be perfect cleared with a queue of `length` pieces, of which
+/// `known` are already known and the rest come from the randomizer.
+///
+/// The randomizer should be in the state after dealing the known pieces; for example, if 3
+/// pieces of the current bag were dealt before the known pieces, use
+/// `SevenBag::default().after(&[first_three, known...].concat())`.
+pub fn pc_chance<const W: usize>(
+    board: BitBoard<W>,
+    known: &[Piece],
+    randomizer: &impl Randomizer,
+    length: usize,
+    hold_allowed: bool,
+    abort: &AtomicBool,
+    placeability_judge: impl Fn(BitBoard<W>, Placement) -> bool,
+) -> f64 {
+    let queues: Vec<_> = queues(randomizer, length.saturating_sub(known.len()))
+        .into_iter()
+        .map(|(unknown, prob)| ([known, &unknown].concat(), prob))
+        .collect();
+    let failures: HashSet<_> = percent(
+        board,
+        queues.iter().map(|(queue, _)| queue),
+        hold_allowed,
+        abort,
+        placeability_judge,
+    )
+    .failures
+    .into_iter()
+    .collect();
+    queues
+        .iter()
+        .filter(|(queue, _)| !failures.contains(queue))
+        .map(|&(_, prob)| prob)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Piece::*;
+
+    fn total(queues: &[(Vec<Piece>, f64)]) -> f64 {
+        queues.iter().map(|&(_, prob)| prob).sum()
+    }
+
+    #[test]
+    fn bags_deal_each_piece_once() {
+        let seven = queues(&SevenBag::default(), 3);
+        assert_eq!(seven.len(), 7 * 6 * 5);
+        assert!((total(&seven) - 1.0).abs() < 1e-9);
+        assert!(seven.iter().all(|(queue, _)| queue[0] != queue[1]));
+
+        // the last piece of a bag is known, and the next bag starts afterwards
+        let last = SevenBag::default().after(&[I, O, T, S, Z, L]).unwrap();
+        assert_eq!(last.next().len(), 1);
+        assert_eq!(last.next()[0].0, J);
+        assert_eq!(last.after(&[J]).unwrap().next().len(), 7);
+        assert!(SevenBag::default().after(&[I, I]).is_none());
+
+        let fourteen = FourteenBag::default().after(&[I, I]).unwrap();
+        assert!(fourteen.next().iter().all(|&(piece, _, _)| piece != I));
+        assert!((total(&queues(&FourteenBag::default(), 3)) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn tgm_rerolls_pieces_in_the_history() {
+        let next = TgmHistory::default().next();
+        let prob = |piece| next.iter().find(|&&(p, _, _)| p == piece).unwrap().1;
+        assert!((prob(Z) - 1.0 / 7f64.powi(4)).abs() < 1e-12);
+        assert!((prob(I) - 400.0 / 2401.0).abs() < 1e-12);
+        assert!((total(&queues(&TgmHistory::default(), 2)) - 1.0).abs() < 1e-9);
+
+        let one_roll = TgmHistory {
+            rolls: 1,
+            ..TgmHistory::default()
+        };
+        assert!(one_roll
+            .next()
+            .iter()
+            .all(|&(_, prob, _)| prob == 1.0 / 7.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one roll")]
+    fn tgm_needs_a_roll() {
+        TgmHistory {
+            rolls: 0,
+            ..TgmHistory::default()
+        }
+        .next();
+    }
+
+    #[test]
+    fn pc_chance_weights_queues() {
+        let board: BitBoard = "
+            XXXX______
+            XXXX______
+        "
+        .parse()
+        .unwrap();
+        // only another I finishes the PC
+        let chance = pc_chance(
+            board,
+            &[O, I],
+            &Memoryless,
+            3,
+            true,
+            &AtomicBool::new(false),
+            placeability::always,
+        );
+        assert!((chance - 1.0 / 7.0).abs() < 1e-9);
+        assert!((total(&queues(&Memoryless, 2)) - 1.0).abs() < 1e-9);
+    }
+}
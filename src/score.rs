@@ -0,0 +1,264 @@
+use crate::spins::Technique;
+use crate::*;
+
+/// Rules that determine how much garbage line clears send.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Rules {
+    Guideline,
+    /// TETR.IO with multiplicative combos, back-to-back chains and all-spins counting as minis.
+    TetrIo,
+    PuyoPuyoTetris,
+}
+
+/// Running score, and the back-to-back and combo state that carries over between clears.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Score {
+    /// Score points, as given at level 1 by the guideline.
+    pub points: u32,
+    /// Lines of garbage sent.
+    pub attack: u32,
+    /// Number of consecutive difficult clears after the first one, or `None` if the last clear
+    /// was not a difficult clear.
+    pub back_to_back: Option<u32>,
+    /// Number of consecutive line clears after the first one, or `None` if the last placement
+    /// did not clear any lines.
+    pub combo: Option<u32>,
+}
+
+impl Score {
+    /// Adds the result of placing a piece that cleared `lines` lines.
+    pub fn place(&mut self, rules: Rules, technique: Technique, lines: u32, perfect_clear: bool) {
+        let spin_points = match technique {
+            Technique::TSpin { .. } => 400,
+            Technique::TSpinMini { .. } => 100,
+            _ => 0,
+        };
+        if lines == 0 {
+            self.points += spin_points;
+            self.combo = None;
+            return;
+        }
+
+        let difficult = lines >= 4
+            || match technique {
+                Technique::TSpin { .. } | Technique::TSpinMini { .. } => true,
+                Technique::AllSpin { .. } => rules == Rules::TetrIo,
+                _ => false,
+            };
+        let back_to_back = if difficult {
+            self.back_to_back.map_or(0, |b2b| b2b + 1)
+        } else {
+            0
+        };
+        let combo = self.combo.map_or(0, |combo| combo + 1);
+
+        let mut points = match technique {
+            Technique::TSpin { .. } => [400, 800, 1200, 1600][lines.min(3) as usize],
+            Technique::TSpinMini { .. } => [100, 200, 400][lines.min(2) as usize],
+            _ => [0, 100, 300, 500, 800][lines.min(4) as usize],
+        };
+        if back_to_back > 0 {
+            points = points * 3 / 2;
+        }
+        points += 50 * combo;
+        if perfect_clear {
+            points += if lines >= 4 && back_to_back > 0 {
+                3200
+            } else {
+                [0, 800, 1200, 1800, 2000][lines.min(4) as usize]
+            };
+        }
+        self.points += points;
+
+        let base = match technique {
+            Technique::TSpin { .. } => 2 * lines,
+            Technique::TSpinMini { .. } => lines - 1,
+            _ if lines >= 4 => 4,
+            _ => lines - 1,
+        };
+        self.attack += match rules {
+            Rules::TetrIo => {
+                let base = base
+                    + if back_to_back > 0 {
+                        tetrio_back_to_back_bonus(back_to_back)
+                    } else {
+                        0
+                    };
+                let attack = if base == 0 && combo >= 2 {
+                    (1.0 + 1.25 * combo as f64).ln()
+                } else {
+                    base as f64 * (1.0 + 0.25 * combo as f64)
+                };
+                attack as u32 + if perfect_clear { 10 } else { 0 }
+            }
+            Rules::Guideline | Rules::PuyoPuyoTetris => {
+                let combo_table: &[u32] = if rules == Rules::Guideline {
+                    &[0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5]
+                } else {
+                    &[0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5]
+                };
+                base + (back_to_back > 0) as u32
+                    + combo_table[(combo as usize).min(combo_table.len() - 1)]
+                    + if perfect_clear { 10 } else { 0 }
+            }
+        };
+
+        self.back_to_back = if difficult { Some(back_to_back) } else { None };
+        self.combo = Some(combo);
+    }
+}
+
+/// Replays a solution, adding the score and attack of each placement to `score`. `techniques`
+/// gives the technique used for each placement, as returned by `spins::classify_solution`.
+pub fn score_solution(
+    rules: Rules,
+    mut score: Score,
+    mut board: BitBoard,
+    solution: &[Placement],
+    techniques: &[Technique],
+) -> Score {
+    for (&placement, &technique) in solution.iter().zip(techniques) {
+        let after = board.combine(placement.board());
+        let lines = (0..MAX_HEIGHT)
+            .filter(|&y| after.line_filled(y) && !board.line_filled(y))
+            .count() as u32;
+        let perfect_clear = lines != 0 && after.lines_cleared() == BitBoard(0);
+        score.place(rules, technique, lines, perfect_clear);
+        board = after;
+    }
+    score
+}
+
+fn tetrio_back_to_back_bonus(back_to_back: u32) -> u32 {
+    const LEVELS: [u32; 7] = [3, 8, 24, 67, 185, 504, 1370];
+    1 + LEVELS
+        .iter()
+        .filter(|&&level| back_to_back >= level)
+        .count() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn place(rules: Rules, clears: &[(Technique, u32, bool)]) -> Score {
+        let mut score = Score::default();
+        for &(technique, lines, perfect_clear) in clears {
+            score.place(rules, technique, lines, perfect_clear);
+        }
+        score
+    }
+
+    #[test]
+    fn guideline_clears() {
+        let tetris = (Technique::HardDrop, 4, false);
+        let tsd = (Technique::TSpin { lines: 2 }, 2, false);
+        assert_eq!(place(Rules::Guideline, &[tetris]).points, 800);
+        assert_eq!(place(Rules::Guideline, &[tetris]).attack, 4);
+        assert_eq!(place(Rules::Guideline, &[tsd]).points, 1200);
+        assert_eq!(place(Rules::Guideline, &[tsd]).attack, 4);
+
+        // back-to-back tetris with a combo of 1
+        let score = place(Rules::Guideline, &[tetris, tetris]);
+        assert_eq!(score.points, 800 + 1200 + 50);
+        assert_eq!(score.attack, 4 + 4 + 1 + 1);
+        assert_eq!(score.back_to_back, Some(1));
+        assert_eq!(score.combo, Some(1));
+    }
+
+    #[test]
+    fn combos_and_back_to_back_end() {
+        let single = (Technique::HardDrop, 1, false);
+        let nothing = (Technique::HardDrop, 0, false);
+        let score = place(Rules::Guideline, &[single, single, single]);
+        assert_eq!(score.points, 100 + 150 + 200);
+        assert_eq!(score.attack, 1 + 1);
+        assert_eq!(
+            place(Rules::PuyoPuyoTetris, &[single, single, single]).attack,
+            1
+        );
+
+        let score = place(Rules::Guideline, &[single, nothing, single]);
+        assert_eq!(score.points, 200);
+        assert_eq!(score.combo, Some(0));
+
+        let tetris = (Technique::HardDrop, 4, false);
+        let score = place(
+            Rules::Guideline,
+            &[tetris, nothing, single, nothing, tetris],
+        );
+        assert_eq!(score.back_to_back, Some(0));
+        assert_eq!(score.attack, 4 + 4);
+    }
+
+    #[test]
+    fn perfect_clears() {
+        let tetris_pc = (Technique::HardDrop, 4, true);
+        assert_eq!(place(Rules::Guideline, &[tetris_pc]).points, 800 + 2000);
+        assert_eq!(place(Rules::Guideline, &[tetris_pc]).attack, 4 + 10);
+        let score = place(
+            Rules::Guideline,
+            &[(Technique::HardDrop, 4, false), tetris_pc],
+        );
+        assert_eq!(score.points, 800 + 1200 + 50 + 3200);
+
+        let single_pc = (Technique::HardDrop, 1, true);
+        assert_eq!(place(Rules::Guideline, &[single_pc]).points, 100 + 800);
+        assert_eq!(place(Rules::TetrIo, &[single_pc]).attack, 10);
+    }
+
+    #[test]
+    fn minis_and_spins_without_lines() {
+        let mini = (Technique::TSpinMini { lines: 0 }, 0, false);
+        let tspin = (Technique::TSpin { lines: 0 }, 0, false);
+        let score = place(Rules::Guideline, &[mini, tspin]);
+        assert_eq!(score.points, 100 + 400);
+        assert_eq!(score.attack, 0);
+        assert_eq!(score.combo, None);
+    }
+
+    #[test]
+    fn tetr_io_attack() {
+        let tetris = (Technique::HardDrop, 4, false);
+        let all_spin = (Technique::AllSpin { lines: 2 }, 2, false);
+        // back-to-back bonus of 1 for a chain of 1, and the combo multiplies the attack
+        assert_eq!(place(Rules::TetrIo, &[tetris, tetris]).attack, 4 + 6);
+        // all-spins count as minis, so they keep back-to-back but send no extra lines
+        let score = place(Rules::TetrIo, &[all_spin]);
+        assert_eq!(score.attack, 1);
+        assert_eq!(score.back_to_back, Some(0));
+        assert_eq!(place(Rules::Guideline, &[all_spin]).back_to_back, None);
+        // combos of singles still send something
+        let single = (Technique::HardDrop, 1, false);
+        assert_eq!(place(Rules::TetrIo, &[single, single, single]).attack, 1);
+        assert_eq!(tetrio_back_to_back_bonus(2), 1);
+        assert_eq!(tetrio_back_to_back_bonus(3), 2);
+        assert_eq!(tetrio_back_to_back_bonus(1370), 8);
+    }
+
+    #[test]
+    fn scores_a_perfect_clear_solution() {
+        let abort = std::sync::atomic::AtomicBool::new(false);
+        let mut solution = None;
+        solve_pc(
+            &[Piece::O; 5],
+            BitBoard::<10>(0),
+            false,
+            true,
+            &abort,
+            placeability::hard_drop_only,
+            |soln| solution = Some(soln.to_vec()),
+        );
+        let solution = solution.unwrap();
+        let techniques = vec![Technique::HardDrop; solution.len()];
+        let score = score_solution(
+            Rules::Guideline,
+            Score::default(),
+            BitBoard(0),
+            &solution,
+            &techniques,
+        );
+        assert_eq!(score.attack, 1 + 10);
+        assert_eq!(score.points, 300 + 1200);
+    }
+}
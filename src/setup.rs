@@ -0,0 +1,256 @@
+use crate::combination::Tetrominoes;
+use crate::solve::{solve_placement_combo, PieceSequence};
+use crate::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Finds the ways to fill exactly the cells of `target` that are empty on `board` using pieces
+/// from the queue, like `solve_pc` does for perfect clears.
+///
+/// `target` must lie within the lowest `height` rows. Each solution is given in placement order.
+/// Placements are always made on the board as it is, so they can't rest on empty target cells
+/// that haven't been filled yet, and they can only cross lines that are completely filled.
+#[allow(clippy::too_many_arguments)]
+pub fn find_setups<const W: usize>(
+    queue: &[Piece],
+    board: BitBoard<W>,
+    target: BitBoard<W>,
+    height: usize,
+    hold_allowed: bool,
+    unique: bool,
+    abort: &AtomicBool,
+    placeability_judge: impl Fn(BitBoard<W>, Placement) -> bool,
+    mut setup_consumer: impl FnMut(&[Placement]),
+) {
+    let to_fill = target.remove(board);
+    let cells = to_fill.0.count_ones() as usize;
+    if cells % Tetrominoes::CELLS != 0 || to_fill.remove(BitBoard::filled(height)) != BitBoard(0) {
+        return;
+    }
+    let pieces = cells / Tetrominoes::CELLS;
+    if queue.len() < pieces {
+        return;
+    }
+    let queue: PieceSequence = queue
+        .iter()
+        .copied()
+        .take(pieces + hold_allowed as usize)
+        .collect();
+
+    // cells outside of the target are treated as filled so that the combinations fill exactly
+    // the target cells
+    let outside = BitBoard::filled(height).remove(to_fill);
+    find_combinations(queue.to_set(), outside, abort, height, |combo| {
+        solve_placement_combo(
+            Tetrominoes,
+            queue,
+            board,
+            combo,
+            hold_allowed,
+            unique,
+            &placeability_judge,
+            &mut setup_consumer,
+        )
+    });
+}
+
+/// Finds the ways to place the next `pieces` pieces of the queue within the lowest `height`
+/// rows such that the resulting board satisfies `is_ok`, for example `no_holes`.
+///
+/// Every order of placements is reported, including different orders that end in the same
+/// board. Placements don't have to be part of any PC, so this is much slower than
+/// `find_setups` for large numbers of pieces.
+#[allow(clippy::too_many_arguments)]
+pub fn find_setups_by<const W: usize>(
+    queue: &[Piece],
+    board: BitBoard<W>,
+    pieces: usize,
+    height: usize,
+    hold_allowed: bool,
+    abort: &AtomicBool,
+    placeability_judge: impl Fn(BitBoard<W>, Placement) -> bool,
+    is_ok: impl Fn(BitBoard<W>) -> bool,
+    mut setup_consumer: impl FnMut(&[Placement]),
+) {
+    if queue.len() < pieces {
+        return;
+    }
+    setups_by(
+        &mut vec![],
+        &queue[..(pieces + hold_allowed as usize).min(queue.len())],
+        None,
+        board,
+        pieces,
+        height,
+        hold_allowed,
+        abort,
+        &placeability_judge,
+        &is_ok,
+        &mut setup_consumer,
+    );
+}
+
+/// Returns true if no empty cell on the board has a filled cell above it.
+pub fn no_holes<const W: usize>(board: BitBoard<W>) -> bool {
+    // cells directly below each cell, with the floor below the bottom row
+    let below = board.0 << W | BitBoard::<W>::ROW;
+    board.0 & !below == 0
+}
+
+#[allow(clippy::too_many_arguments)]
+fn setups_by<const W: usize>(
+    placements: &mut Vec<Placement>,
+    queue: &[Piece],
+    hold: Option<Piece>,
+    board: BitBoard<W>,
+    pieces: usize,
+    height: usize,
+    hold_allowed: bool,
+    abort: &AtomicBool,
+    placeability_judge: &impl Fn(BitBoard<W>, Placement) -> bool,
+    is_ok: &impl Fn(BitBoard<W>) -> bool,
+    setup_consumer: &mut impl FnMut(&[Placement]),
+) {
+    if placements.len() == pieces {
+        if is_ok(board) {
+            setup_consumer(placements);
+        }
+        return;
+    }
+    if abort.load(Ordering::Acquire) {
+        return;
+    }
+
+    // the piece to place, and the queue and hold afterwards
+    let mut options = vec![];
+    if let Some((&current, rest)) = queue.split_first() {
+        options.push((current, rest, hold));
+        if hold_allowed {
+            match hold {
+                Some(held) if held != current => options.push((held, rest, Some(current))),
+                None => {
+                    if let Some((&next, rest)) = rest.split_first() {
+                        options.push((next, rest, Some(current)));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let area = BitBoard::<W>::filled(height);
+    for (piece, queue, hold) in options {
+        for (placement, _) in placeability::placements(board, piece, placeability_judge) {
+            if placement.board().remove(area) != BitBoard(0) {
+                continue;
+            }
+            placements.push(placement);
+            setups_by(
+                placements,
+                queue,
+                hold,
+                board.combine(placement.board()),
+                pieces,
+                height,
+                hold_allowed,
+                abort,
+                placeability_judge,
+                is_ok,
+                setup_consumer,
+            );
+            placements.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Piece::*;
+
+    fn setups(queue: &[Piece], target: &str, height: usize) -> Vec<Vec<Placement>> {
+        let target: BitBoard = target.parse().unwrap();
+        let mut setups = vec![];
+        find_setups(
+            queue,
+            BitBoard(0),
+            target,
+            height,
+            true,
+            true,
+            &AtomicBool::new(false),
+            placeability::always,
+            |setup| setups.push(setup.to_vec()),
+        );
+        setups
+    }
+
+    #[test]
+    fn fills_exactly_the_target() {
+        let target = "
+            XXXX______
+            XXXX______
+        ";
+        let stacked = setups(&[I, I], target, 2);
+        assert_eq!(stacked.len(), 1);
+        let [bottom, top] = stacked[0][..] else {
+            panic!("expected two placements");
+        };
+        let bottom_row: BitBoard = "XXXX______".parse().unwrap();
+        assert_eq!(bottom.board(), bottom_row);
+        assert_eq!(top.board(), BitBoard::<10>(bottom_row.0 << 10));
+
+        assert_eq!(setups(&[O, O, I], target, 2).len(), 1);
+        assert!(setups(&[T, T], target, 2).is_empty());
+        // the target has to fit in the height and be made of whole pieces
+        assert!(setups(&[I, I], target, 1).is_empty());
+        assert!(setups(&[I, I], "XXX_______", 1).is_empty());
+    }
+
+    #[test]
+    fn finds_flat_setups() {
+        let count = |queue: &[Piece], hold_allowed| {
+            let mut count = 0;
+            find_setups_by(
+                queue,
+                BitBoard::<10>(0),
+                1,
+                2,
+                hold_allowed,
+                &AtomicBool::new(false),
+                placeability::always,
+                no_holes,
+                |_| count += 1,
+            );
+            count
+        };
+        // the only T placements without holes point up
+        assert_eq!(count(&[T], false), 8);
+        assert_eq!(count(&[O], false), 9);
+        assert_eq!(count(&[T, O], false), 8);
+        assert_eq!(count(&[T, O], true), 17);
+    }
+
+    #[test]
+    fn holes_are_covered_cells() {
+        let holes = |board: &str| !no_holes(board.parse::<BitBoard>().unwrap());
+        assert!(!holes("__________"));
+        assert!(!holes(
+            "
+            X_________
+            XX_____XXX
+            "
+        ));
+        assert!(holes(
+            "
+            XX________
+            X_________
+            "
+        ));
+        assert!(holes(
+            "
+            _X________
+            __________
+            "
+        ));
+    }
+}
@@ -1,50 +1,155 @@
+use crate::combination::{PieceKinds, Tetrominoes};
 use crate::*;
+use std::ops::RangeInclusive;
 use std::sync::atomic::AtomicBool;
 
-pub fn solve_pc(
+/// The PC heights `solve_pc` and `SolutionIter::new` try. Taller PCs, up to `MAX_HEIGHT`, can be
+/// found with `solve_pc_all_heights`.
+pub(crate) const DEFAULT_HEIGHTS: RangeInclusive<usize> = 0..=6;
+
+pub fn solve_pc<const W: usize>(
     queue: &[Piece],
-    board: BitBoard,
+    board: BitBoard<W>,
     hold_allowed: bool,
     unique: bool,
     abort: &AtomicBool,
-    placeability_judge: impl Fn(BitBoard, Placement) -> bool,
+    placeability_judge: impl Fn(BitBoard<W>, Placement) -> bool,
     mut pc_consumer: impl FnMut(&[Placement]),
 ) {
-    solve_pc_prep(queue, board, hold_allowed, |queue, height| {
-        let mut found = false;
-        find_combinations(queue.to_set(), board, abort, height, |combo| {
-            solve_placement_combo(
-                queue,
-                board,
-                combo,
-                hold_allowed,
-                unique,
-                &placeability_judge,
-                |soln| {
-                    found = true;
-                    pc_consumer(soln)
-                },
-            )
-        });
-        found
-    });
+    solve_pc_st(
+        queue,
+        board,
+        hold_allowed,
+        unique,
+        abort,
+        DEFAULT_HEIGHTS,
+        true,
+        placeability_judge,
+        |soln, _| pc_consumer(soln),
+    );
 }
 
-pub fn solve_pc_mt(
+pub fn solve_pc_mt<const W: usize>(
     queue: &[Piece],
-    board: BitBoard,
+    board: BitBoard<W>,
     hold_allowed: bool,
     unique: bool,
     abort: &AtomicBool,
-    placeability_judge: impl Fn(BitBoard, Placement) -> bool + Sync,
-    pc_consumer: impl FnMut(&[Placement]) + Clone + Send,
+    placeability_judge: impl Fn(BitBoard<W>, Placement) -> bool + Sync,
+    mut pc_consumer: impl FnMut(&[Placement]) + Clone + Send,
+) {
+    solve_pc_multithreaded(
+        queue,
+        board,
+        hold_allowed,
+        unique,
+        abort,
+        DEFAULT_HEIGHTS,
+        true,
+        placeability_judge,
+        move |soln, _| pc_consumer(soln),
+    );
+}
+
+/// Like `solve_pc`, but finds the solutions at every PC height in `heights` instead of stopping
+/// at the lowest height that has any. `heights` can go up to `MAX_HEIGHT`, while `solve_pc` only
+/// tries PCs up to 6 lines tall. The consumer also receives the height of the PC.
+#[allow(clippy::too_many_arguments)]
+pub fn solve_pc_all_heights<const W: usize>(
+    queue: &[Piece],
+    board: BitBoard<W>,
+    hold_allowed: bool,
+    unique: bool,
+    abort: &AtomicBool,
+    heights: RangeInclusive<usize>,
+    placeability_judge: impl Fn(BitBoard<W>, Placement) -> bool,
+    pc_consumer: impl FnMut(&[Placement], usize),
+) {
+    solve_pc_st(
+        queue,
+        board,
+        hold_allowed,
+        unique,
+        abort,
+        heights,
+        false,
+        placeability_judge,
+        pc_consumer,
+    );
+}
+
+/// Like `solve_pc_mt`, but finds the solutions at every PC height in `heights` instead of
+/// stopping at the lowest height that has any. The consumer also receives the height of the PC.
+#[allow(clippy::too_many_arguments)]
+pub fn solve_pc_all_heights_mt<const W: usize>(
+    queue: &[Piece],
+    board: BitBoard<W>,
+    hold_allowed: bool,
+    unique: bool,
+    abort: &AtomicBool,
+    heights: RangeInclusive<usize>,
+    placeability_judge: impl Fn(BitBoard<W>, Placement) -> bool + Sync,
+    pc_consumer: impl FnMut(&[Placement], usize) + Clone + Send,
+) {
+    solve_pc_multithreaded(
+        queue,
+        board,
+        hold_allowed,
+        unique,
+        abort,
+        heights,
+        false,
+        placeability_judge,
+        pc_consumer,
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+fn solve_pc_st<const W: usize>(
+    queue: &[Piece],
+    board: BitBoard<W>,
+    hold_allowed: bool,
+    unique: bool,
+    abort: &AtomicBool,
+    heights: RangeInclusive<usize>,
+    stop_at_first: bool,
+    placeability_judge: impl Fn(BitBoard<W>, Placement) -> bool,
+    mut pc_consumer: impl FnMut(&[Placement], usize),
+) {
+    let mut solutions = SolutionIter::with_heights(
+        queue,
+        board,
+        hold_allowed,
+        unique,
+        abort,
+        heights,
+        stop_at_first,
+        placeability_judge,
+    );
+    while solutions.advance() {
+        pc_consumer(solutions.solution(), solutions.height);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn solve_pc_multithreaded<const W: usize>(
+    queue: &[Piece],
+    board: BitBoard<W>,
+    hold_allowed: bool,
+    unique: bool,
+    abort: &AtomicBool,
+    heights: RangeInclusive<usize>,
+    stop_at_first: bool,
+    placeability_judge: impl Fn(BitBoard<W>, Placement) -> bool + Sync,
+    pc_consumer: impl FnMut(&[Placement], usize) + Clone + Send,
 ) {
     let placeability_judge = &placeability_judge;
-    solve_pc_prep(queue, board, hold_allowed, |queue, height| {
+    for (height, queue) in pc_queues(queue, board, hold_allowed, heights) {
         let found = &std::sync::atomic::AtomicBool::new(false);
         let mut pc_consumer = pc_consumer.clone();
         find_combinations_mt(queue.to_set(), board, abort, height, move |combo| {
             solve_placement_combo(
+                Tetrominoes,
                 queue,
                 board,
                 combo,
@@ -53,67 +158,271 @@ pub fn solve_pc_mt(
                 placeability_judge,
                 |soln| {
                     found.store(true, std::sync::atomic::Ordering::Release);
-                    pc_consumer(soln)
+                    pc_consumer(soln, height)
                 },
             )
         });
-        found.load(std::sync::atomic::Ordering::Acquire)
-    });
+        if found.load(std::sync::atomic::Ordering::Acquire) && stop_at_first {
+            break;
+        }
+    }
+}
+
+/// The state of a game at the start of a turn.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct GameState<const W: usize = 10> {
+    pub board: BitBoard<W>,
+    pub current: Piece,
+    pub hold: Option<Piece>,
+    /// Whether hold was already used this turn, so the current piece must be placed.
+    pub hold_used: bool,
+    pub preview: Vec<Piece>,
+}
+
+impl<const W: usize> GameState<W> {
+    /// The queue to solve with, along with the piece the first placement must use, if any.
+    ///
+    /// A held piece can be swapped with the current piece at any time, which is the same as
+    /// being able to place either of the first two pieces of the queue, so it goes first.
+    fn queue(&self) -> (Vec<Piece>, Option<Piece>) {
+        let mut queue = vec![];
+        let forced = match self.hold {
+            Some(held) if self.hold_used => {
+                queue.push(self.current);
+                queue.push(held);
+                Some(self.current)
+            }
+            Some(held) => {
+                queue.push(held);
+                queue.push(self.current);
+                None
+            }
+            None => {
+                queue.push(self.current);
+                None
+            }
+        };
+        queue.extend_from_slice(&self.preview);
+        (queue, forced)
+    }
+}
+
+/// Like `solve_pc` with hold allowed, but starting from a game state.
+pub fn solve_pc_from_state<const W: usize>(
+    state: &GameState<W>,
+    unique: bool,
+    abort: &AtomicBool,
+    placeability_judge: impl Fn(BitBoard<W>, Placement) -> bool,
+    pc_consumer: impl FnMut(&[Placement]),
+) {
+    let (queue, forced) = state.queue();
+    let board = state.board;
+    solve_pc(
+        &queue,
+        board,
+        true,
+        unique,
+        abort,
+        // the board only changes once the first piece is placed
+        |on, placement| {
+            (on != board || forced.map_or(true, |p| p == placement.kind.piece()))
+                && placeability_judge(on, placement)
+        },
+        pc_consumer,
+    );
+}
+
+/// Like `solve_pc_mt` with hold allowed, but starting from a game state.
+pub fn solve_pc_mt_from_state<const W: usize>(
+    state: &GameState<W>,
+    unique: bool,
+    abort: &AtomicBool,
+    placeability_judge: impl Fn(BitBoard<W>, Placement) -> bool + Sync,
+    pc_consumer: impl FnMut(&[Placement]) + Clone + Send,
+) {
+    let (queue, forced) = state.queue();
+    let board = state.board;
+    solve_pc_mt(
+        &queue,
+        board,
+        true,
+        unique,
+        abort,
+        // the board only changes once the first piece is placed
+        |on, placement| {
+            (on != board || forced.map_or(true, |p| p == placement.kind.piece()))
+                && placeability_judge(on, placement)
+        },
+        pc_consumer,
+    );
 }
 
-fn solve_pc_prep(
+/// Like `solve_pc`, but gives each solution with the hold and queue details from
+/// `SolutionInfo`.
+pub fn solve_pc_with_info<const W: usize>(
     queue: &[Piece],
-    board: BitBoard,
+    board: BitBoard<W>,
     hold_allowed: bool,
-    mut do_solve: impl FnMut(PieceSequence, usize) -> bool,
+    unique: bool,
+    abort: &AtomicBool,
+    placeability_judge: impl Fn(BitBoard<W>, Placement) -> bool,
+    mut pc_consumer: impl FnMut(&SolutionInfo),
 ) {
-    let mut lowest_height = 0;
-    for y in 0..6 {
-        if board.0 >> y * 10 & (1 << 10) - 1 != 0 {
-            lowest_height = y + 1;
+    solve_pc(
+        queue,
+        board,
+        hold_allowed,
+        unique,
+        abort,
+        placeability_judge,
+        |soln| pc_consumer(&SolutionInfo::from_solution(queue, soln)),
+    );
+}
+
+/// How a solution uses the queue and hold, starting with an empty hold.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SolutionInfo {
+    pub placements: Vec<Placement>,
+    /// Whether hold was pressed before each placement.
+    pub holds: Vec<bool>,
+    /// The pieces taken from the queue, in queue order.
+    pub consumed: Vec<Piece>,
+    /// The piece in hold after the last placement.
+    pub hold: Option<Piece>,
+    /// The pieces of the queue that were never taken.
+    pub remaining: Vec<Piece>,
+}
+
+impl SolutionInfo {
+    /// Works out where hold was pressed for the placements to be made in order from the queue.
+    /// Hold is only pressed when the current piece can't be placed. Returns `None` if the
+    /// placements can't be made in that order.
+    pub fn new(queue: &[Piece], placements: &[Placement], hold_allowed: bool) -> Option<Self> {
+        let (info, possible) = Self::replay(queue, placements);
+        if possible && (hold_allowed || !info.holds.contains(&true)) {
+            Some(info)
+        } else {
+            None
         }
     }
-    let unfilled = 10 * lowest_height - board.0.count_ones() as usize;
-    if unfilled % 2 != 0 {
-        // can never fill an odd number of cells
-        return;
-    } else if unfilled % 4 != 0 {
-        // need to fill an extra line to get a PC
-        lowest_height += 1;
-    }
-    if lowest_height == 0 {
-        lowest_height = 2;
+
+    /// Like `new`, for a solution the solver found from `queue`, which can always be made in
+    /// order.
+    pub(crate) fn from_solution(queue: &[Piece], placements: &[Placement]) -> Self {
+        Self::replay(queue, placements).0
     }
 
-    for height in (lowest_height..=6).step_by(2) {
-        let unfilled = 10 * height - board.0.count_ones() as usize;
-        let pieces = unfilled / 4;
-        if queue.len() < pieces {
-            break;
+    /// Makes the placements from the queue, holding whenever the current piece doesn't match.
+    /// Also returns whether every placement got the piece it needed.
+    fn replay(queue: &[Piece], placements: &[Placement]) -> (Self, bool) {
+        let mut holds = vec![];
+        let mut hold = None;
+        let mut next = 0;
+        let mut possible = true;
+        for placement in placements {
+            let piece = placement.kind.piece();
+            let current = queue.get(next).copied();
+            if current == Some(piece) {
+                holds.push(false);
+                next += 1;
+                continue;
+            }
+            possible &= current.is_some()
+                && match hold {
+                    Some(held) => held == piece,
+                    None => queue.get(next + 1) == Some(&piece),
+                };
+            next += if hold.is_some() { 1 } else { 2 };
+            hold = current;
+            holds.push(true);
         }
-        let queue: PieceSequence = queue
-            .iter()
-            .copied()
-            .take(pieces + hold_allowed as usize)
-            .collect();
+        let next = next.min(queue.len());
+        let info = SolutionInfo {
+            placements: placements.to_vec(),
+            holds,
+            consumed: queue[..next].to_vec(),
+            hold,
+            remaining: queue[next..].to_vec(),
+        };
+        (info, possible)
+    }
+}
 
-        if do_solve(queue, height) {
-            break;
+/// The PC heights in `heights` that the queue has enough pieces for, lowest first, with the
+/// part of the queue each one can use.
+fn pc_queues<const W: usize>(
+    queue: &[Piece],
+    board: BitBoard<W>,
+    hold_allowed: bool,
+    heights: RangeInclusive<usize>,
+) -> Vec<(usize, PieceSequence)> {
+    pc_heights(board, Tetrominoes::CELLS)
+        .into_iter()
+        .filter(|(height, _)| heights.contains(height))
+        .take_while(|&(_, pieces)| queue.len() >= pieces)
+        .map(|(height, pieces)| {
+            let queue = queue
+                .iter()
+                .copied()
+                .take(pieces + hold_allowed as usize)
+                .collect();
+            (height, queue)
+        })
+        .collect()
+}
+
+/// Heights a PC can be done at on the board, lowest first, and the number of pieces of
+/// `piece_cells` cells each needs.
+pub(crate) fn pc_heights<const W: usize>(
+    board: BitBoard<W>,
+    piece_cells: usize,
+) -> Vec<(usize, usize)> {
+    let mut lowest_height = 1;
+    for y in 0..BitBoard::<W>::HEIGHT {
+        if board.0 >> (y * W) & BitBoard::<W>::ROW != 0 {
+            lowest_height = y + 1;
         }
     }
+    let filled = board.0.count_ones() as usize;
+    // each extra line adds `W` cells to fill, so the heights where the cells to fill are a
+    // multiple of the piece size come in steps of a few lines, e.g. every 1, 2 or 4 lines for
+    // tetrominoes depending on the width
+    let step = piece_cells / gcd(W, piece_cells);
+    let first = match (lowest_height..lowest_height + step)
+        .find(|&h| (W * h - filled) % piece_cells == 0)
+    {
+        Some(first) => first,
+        // e.g. an odd number of filled cells on an even width, which no height can clear
+        None => return vec![],
+    };
+
+    (first..=BitBoard::<W>::HEIGHT)
+        .step_by(step)
+        .map(|height| (height, (W * height - filled) / piece_cells))
+        .collect()
 }
 
-pub fn solve_placement_combination(
+pub(crate) fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn solve_placement_combination<const W: usize>(
     queue: &[Piece],
-    board: BitBoard,
+    board: BitBoard<W>,
     combination: &[Placement],
     hold_allowed: bool,
     unique: bool,
-    abort: &AtomicBool,
-    placability_judge: impl Fn(BitBoard, Placement) -> bool,
+    _abort: &AtomicBool,
+    placability_judge: impl Fn(BitBoard<W>, Placement) -> bool,
     pc_consumer: impl FnMut(&[Placement]),
 ) {
     solve_placement_combo(
+        Tetrominoes,
         queue.iter().copied().collect(),
         board,
         combination,
@@ -124,58 +433,263 @@ pub fn solve_placement_combination(
     );
 }
 
-fn solve_placement_combo(
-    queue: PieceSequence,
-    board: BitBoard,
-    combination: &[Placement],
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn solve_placement_combo<const W: usize, K: PieceKinds<W>>(
+    kinds: K,
+    queue: PieceSequence<K::Piece>,
+    board: BitBoard<W>,
+    combination: &[K::Placement],
     hold_allowed: bool,
     unique: bool,
-    placability_judge: impl Fn(BitBoard, Placement) -> bool,
-    mut pc_consumer: impl FnMut(&[Placement]),
+    placability_judge: impl Fn(BitBoard<W>, K::Placement) -> bool,
+    mut pc_consumer: impl FnMut(&[K::Placement]),
 ) {
-    let mut combo = ArrayVec::new();
-    combo.try_extend_from_slice(combination).unwrap();
-    solve(
-        &mut ArrayVec::new(),
-        queue,
-        board,
-        &mut combo,
-        hold_allowed,
-        unique,
-        &placability_judge,
-        &mut pc_consumer,
-    );
+    let mut permutations =
+        Permutations::new(kinds, queue, board, combination, hold_allowed, unique);
+    while permutations.advance(&placability_judge) {
+        pc_consumer(&permutations.permutation);
+    }
 }
 
-fn solve(
-    permutation: &mut ArrayVec<[Placement; 15]>,
-    queue: PieceSequence,
-    board: BitBoard,
-    remaining: &mut ArrayVec<[Placement; 15]>,
+/// Finds the same solutions as `solve_pc`, but one at a time as they're asked for.
+///
+/// The search stack is kept in the iterator, so the search can be stopped after any number of
+/// solutions, or paused and picked up again later. `next_solution` gives each solution without
+/// allocating; the `Iterator` impl gives each one as a `Vec`.
+pub struct SolutionIter<'a, const W: usize, J> {
+    board: BitBoard<W>,
     hold_allowed: bool,
     unique: bool,
-    placability_judge: &impl Fn(BitBoard, Placement) -> bool,
-    pc_consumer: &mut impl FnMut(&[Placement]),
-) -> Option<()> {
-    if remaining.is_empty() {
-        pc_consumer(permutation);
-        if unique {
-            None
+    abort: &'a AtomicBool,
+    placeability_judge: J,
+    /// The heights left to search, and the part of the queue each one can use.
+    heights: std::vec::IntoIter<(usize, PieceSequence)>,
+    stop_at_first: bool,
+    /// Whether any solutions were found at the current height.
+    found: bool,
+    height: usize,
+    queue: PieceSequence,
+    combinations: Option<CombinationIter<'a, W>>,
+    permutations: Option<Permutations<W, Tetrominoes>>,
+}
+
+impl<'a, const W: usize, J: Fn(BitBoard<W>, Placement) -> bool> SolutionIter<'a, W, J> {
+    pub fn new(
+        queue: &[Piece],
+        board: BitBoard<W>,
+        hold_allowed: bool,
+        unique: bool,
+        abort: &'a AtomicBool,
+        placeability_judge: J,
+    ) -> Self {
+        SolutionIter::with_heights(
+            queue,
+            board,
+            hold_allowed,
+            unique,
+            abort,
+            DEFAULT_HEIGHTS,
+            true,
+            placeability_judge,
+        )
+    }
+
+    /// Like `new`, but finds the solutions at every PC height in `heights` instead of stopping
+    /// at the lowest height that has any, like `solve_pc_all_heights`.
+    pub fn all_heights(
+        queue: &[Piece],
+        board: BitBoard<W>,
+        hold_allowed: bool,
+        unique: bool,
+        abort: &'a AtomicBool,
+        heights: RangeInclusive<usize>,
+        placeability_judge: J,
+    ) -> Self {
+        SolutionIter::with_heights(
+            queue,
+            board,
+            hold_allowed,
+            unique,
+            abort,
+            heights,
+            false,
+            placeability_judge,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn with_heights(
+        queue: &[Piece],
+        board: BitBoard<W>,
+        hold_allowed: bool,
+        unique: bool,
+        abort: &'a AtomicBool,
+        heights: RangeInclusive<usize>,
+        stop_at_first: bool,
+        placeability_judge: J,
+    ) -> Self {
+        SolutionIter {
+            board,
+            hold_allowed,
+            unique,
+            abort,
+            placeability_judge,
+            heights: pc_queues(queue, board, hold_allowed, heights).into_iter(),
+            stop_at_first,
+            found: false,
+            height: 0,
+            queue: std::iter::empty().collect(),
+            combinations: None,
+            permutations: None,
+        }
+    }
+
+    /// The height of the PC the last solution was found at.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Finds the next solution, or returns `None` once there are no more.
+    pub fn next_solution(&mut self) -> Option<&[Placement]> {
+        if self.advance() {
+            Some(self.solution())
         } else {
-            Some(())
+            None
         }
-    } else {
-        // we have the invariant that the range 0..n remains the same from one iteration to the next
-        // we do still have to mutate the remaining vec in ways that the compiler can only view as
-        // aribtrary, though, so we can't actually use normal iteration here.
-        for i in 0..remaining.len() {
-            let placement = remaining[i];
+    }
+
+    fn solution(&self) -> &[Placement] {
+        &self.permutations.as_ref().unwrap().permutation
+    }
+
+    fn advance(&mut self) -> bool {
+        loop {
+            if let Some(permutations) = &mut self.permutations {
+                if permutations.advance(&self.placeability_judge) {
+                    self.found = true;
+                    return true;
+                }
+                self.permutations = None;
+            }
+            if let Some(combinations) = &mut self.combinations {
+                if let Some(combo) = combinations.next_combination() {
+                    self.permutations = Some(Permutations::new(
+                        Tetrominoes,
+                        self.queue,
+                        self.board,
+                        combo,
+                        self.hold_allowed,
+                        self.unique,
+                    ));
+                    continue;
+                }
+                self.combinations = None;
+                if self.found && self.stop_at_first {
+                    self.heights = vec![].into_iter();
+                }
+            }
+            let (height, queue) = match self.heights.next() {
+                Some(next) => next,
+                None => return false,
+            };
+            self.height = height;
+            self.queue = queue;
+            self.found = false;
+            self.combinations = Some(CombinationIter::new(
+                queue.to_set(),
+                self.board,
+                self.abort,
+                height,
+            ));
+        }
+    }
+}
+
+impl<const W: usize, J: Fn(BitBoard<W>, Placement) -> bool> Iterator for SolutionIter<'_, W, J> {
+    type Item = Vec<Placement>;
 
-            if !queue.is_next(placement.kind.piece(), hold_allowed) {
+    fn next(&mut self) -> Option<Vec<Placement>> {
+        self.next_solution().map(<[_]>::to_vec)
+    }
+}
+
+/// The orders a combination can be placed in, found one at a time.
+struct Permutations<const W: usize, K: PieceKinds<W>> {
+    kinds: K,
+    permutation: ArrayVec<[K::Placement; 32]>,
+    remaining: ArrayVec<[K::Placement; 32]>,
+    /// The queue and board before each placement of the permutation, and the index in
+    /// `remaining` of the next placement to try there.
+    stack: Vec<(PieceSequence<K::Piece>, BitBoard<W>, usize)>,
+    hold_allowed: bool,
+    unique: bool,
+    /// Whether the permutation is complete and was already given out.
+    found: bool,
+    /// Whether the combination is empty, so the empty permutation is yet to be given out.
+    empty: bool,
+}
+
+impl<const W: usize, K: PieceKinds<W>> Permutations<W, K> {
+    fn new(
+        kinds: K,
+        queue: PieceSequence<K::Piece>,
+        board: BitBoard<W>,
+        combination: &[K::Placement],
+        hold_allowed: bool,
+        unique: bool,
+    ) -> Self {
+        let mut remaining = ArrayVec::new();
+        remaining.try_extend_from_slice(combination).unwrap();
+        Permutations {
+            kinds,
+            permutation: ArrayVec::new(),
+            remaining,
+            stack: if combination.is_empty() {
+                vec![]
+            } else {
+                vec![(queue, board, 0)]
+            },
+            hold_allowed,
+            unique,
+            found: false,
+            empty: combination.is_empty(),
+        }
+    }
+
+    fn advance(&mut self, placability_judge: &impl Fn(BitBoard<W>, K::Placement) -> bool) -> bool {
+        if self.empty {
+            self.empty = false;
+            return true;
+        }
+        if self.found {
+            self.found = false;
+            if self.unique {
+                self.stack.clear();
+                return false;
+            }
+            self.undo();
+        }
+        // we have the invariant that the range 0..n of remaining is the same every time a frame
+        // is returned to. we do still have to mutate the remaining vec in ways that the compiler
+        // can only view as aribtrary, though, so we can't actually use normal iteration here.
+        while let Some(&mut (queue, board, ref mut i)) = self.stack.last_mut() {
+            if *i == self.remaining.len() {
+                self.stack.pop();
+                if !self.stack.is_empty() {
+                    self.undo();
+                }
+                continue;
+            }
+            let index = *i;
+            *i += 1;
+            let placement = self.remaining[index];
+
+            let piece = self.kinds.piece(placement);
+            if !queue.is_next(piece, self.hold_allowed) {
                 // can't place this placement since it's neither next nor obtainable through hold
                 continue;
             }
-            if !placement.supported_after_clears(board) {
+            if !self.kinds.supported_after_clears(placement, board) {
                 // unsupported placement obviously can't come next
                 continue;
             }
@@ -185,55 +699,68 @@ fn solve(
                 continue;
             }
 
-            let new_board = board.combine(placement.board());
+            let new_board = board.combine(self.kinds.board(placement));
             let mut new_queue = queue;
-            new_queue.remove(placement.kind.piece());
-            remaining.swap_remove(i);
-            permutation.push(placement);
-
-            solve(
-                permutation,
-                new_queue,
-                new_board,
-                remaining,
-                hold_allowed,
-                unique,
-                placability_judge,
-                pc_consumer,
-            )?;
-
-            permutation.pop();
-            remaining.push(placement);
-            let last_index = remaining.len() - 1;
-            remaining.swap(i, last_index);
-            // the above restores the original state of the remaining vec
+            new_queue.remove(piece);
+            self.remaining.swap_remove(index);
+            self.permutation.push(placement);
+
+            if self.remaining.is_empty() {
+                self.found = true;
+                return true;
+            }
+            self.stack.push((new_queue, new_board, 0));
         }
+        false
+    }
 
-        Some(())
+    /// Takes back the last placement of the permutation, restoring the original state of the
+    /// remaining vec.
+    fn undo(&mut self) {
+        let placement = self.permutation.pop().unwrap();
+        self.remaining.push(placement);
+        let last_index = self.remaining.len() - 1;
+        let i = self.stack.last().unwrap().2 - 1;
+        self.remaining.swap(i, last_index);
     }
 }
 
 #[derive(Copy, Clone, Debug, Eq)]
-struct PieceSequence {
-    seq: [Piece; 16],
+pub(crate) struct PieceSequence<P = Piece> {
+    seq: [P; 32],
     count: u8,
 }
 
-impl PieceSequence {
-    fn is_next(&self, piece: Piece, hold_allowed: bool) -> bool {
+impl<P: Copy + PartialEq> PieceSequence<P> {
+    fn is_next(&self, piece: P, hold_allowed: bool) -> bool {
         self.count != 0
             && (self.seq[self.count as usize - 1] == piece
                 || hold_allowed && self.count != 1 && self.seq[self.count as usize - 2] == piece)
     }
 
-    fn remove(&mut self, piece: Piece) {
+    fn remove(&mut self, piece: P) {
         if self.seq[self.count as usize - 1] != piece {
             self.seq[self.count as usize - 2] = self.seq[self.count as usize - 1];
         }
         self.count -= 1;
     }
 
-    fn to_set(&self) -> PieceSet {
+    /// Takes up to 32 pieces, using `filler` for the unused slots.
+    fn from_pieces(pieces: impl IntoIterator<Item = P>, filler: P) -> Self {
+        let mut seq = [filler; 32];
+        let mut count = 0;
+        for p in pieces.into_iter().take(32) {
+            seq[count as usize] = p;
+            count += 1;
+        }
+        seq.rotate_right(32 - count as usize);
+        seq.reverse();
+        PieceSequence { seq, count }
+    }
+}
+
+impl PieceSequence {
+    pub(crate) fn to_set(self) -> PieceSet {
         let mut set = PieceSet::default();
         for i in 0..self.count {
             set = set.with(self.seq[i as usize]);
@@ -244,19 +771,17 @@ impl PieceSequence {
 
 impl std::iter::FromIterator<Piece> for PieceSequence {
     fn from_iter<T: IntoIterator<Item = Piece>>(iter: T) -> Self {
-        let mut seq = [Piece::S; 16];
-        let mut count = 0;
-        for p in iter.into_iter().take(16) {
-            seq[count as usize] = p;
-            count += 1;
-        }
-        seq.rotate_right(16 - count as usize);
-        seq.reverse();
-        PieceSequence { seq, count }
+        PieceSequence::from_pieces(iter, Piece::S)
+    }
+}
+
+impl std::iter::FromIterator<usize> for PieceSequence<usize> {
+    fn from_iter<T: IntoIterator<Item = usize>>(iter: T) -> Self {
+        PieceSequence::from_pieces(iter, 0)
     }
 }
 
-impl std::cmp::PartialEq for PieceSequence {
+impl<P: PartialEq> std::cmp::PartialEq for PieceSequence<P> {
     fn eq(&self, other: &Self) -> bool {
         if self.count != other.count {
             return false;
@@ -270,7 +795,7 @@ impl std::cmp::PartialEq for PieceSequence {
     }
 }
 
-impl std::hash::Hash for PieceSequence {
+impl<P: std::hash::Hash> std::hash::Hash for PieceSequence<P> {
     fn hash<H: std::hash::Hasher>(&self, h: &mut H) {
         h.write_u8(self.count);
         for i in 0..self.count as usize {
@@ -278,3 +803,379 @@ impl std::hash::Hash for PieceSequence {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use Piece::*;
+
+    fn pco() -> BitBoard {
+        "
+        XXXX____XX
+        XXXX___XXX
+        XXXX__XXXX
+        XXXX___XXX
+        "
+        .parse()
+        .unwrap()
+    }
+
+    fn two_lines() -> BitBoard {
+        "
+        XXXX______
+        XXXX______
+        "
+        .parse()
+        .unwrap()
+    }
+
+    fn callback_solutions(queue: &[Piece], board: BitBoard, unique: bool) -> Vec<Vec<Placement>> {
+        let mut solutions = vec![];
+        solve_pc(
+            queue,
+            board,
+            true,
+            unique,
+            &AtomicBool::new(false),
+            placeability::simple_srs_spins,
+            |soln| solutions.push(soln.to_vec()),
+        );
+        solutions
+    }
+
+    fn mt_solutions(queue: &[Piece], board: BitBoard, unique: bool) -> HashSet<Vec<Placement>> {
+        let solutions = std::sync::Mutex::new(HashSet::new());
+        solve_pc_mt(
+            queue,
+            board,
+            true,
+            unique,
+            &AtomicBool::new(false),
+            placeability::simple_srs_spins,
+            |soln| {
+                solutions.lock().unwrap().insert(soln.to_vec());
+            },
+        );
+        solutions.into_inner().unwrap()
+    }
+
+    #[test]
+    fn iterator_matches_the_callbacks() {
+        let abort = AtomicBool::new(false);
+        for (queue, board) in [(&[I, T, L, J, O][..], pco()), (&[O, I, I, T], two_lines())] {
+            for unique in [false, true] {
+                let expected = callback_solutions(queue, board, unique);
+                assert!(!expected.is_empty());
+                let solutions: Vec<_> = SolutionIter::new(
+                    queue,
+                    board,
+                    true,
+                    unique,
+                    &abort,
+                    placeability::simple_srs_spins,
+                )
+                .collect();
+                assert_eq!(solutions, expected);
+                assert_eq!(
+                    solutions.into_iter().collect::<HashSet<_>>(),
+                    mt_solutions(queue, board, unique)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn unique_keeps_one_order_per_combination() {
+        let combination = |soln: &Vec<Placement>| {
+            let mut boards: Vec<_> = soln.iter().map(|p| p.board::<10>().0).collect();
+            boards.sort_unstable();
+            boards
+        };
+        let queue = [I, T, L, J, O];
+        let solutions = callback_solutions(&queue, pco(), false);
+        let all: HashSet<_> = solutions.iter().map(combination).collect();
+        let unique = callback_solutions(&queue, pco(), true);
+        assert!(unique.len() < solutions.len());
+        assert_eq!(unique.iter().map(combination).collect::<HashSet<_>>(), all);
+        assert_eq!(unique.len(), all.len());
+    }
+
+    #[test]
+    fn solves_the_narrowest_and_widest_boards() {
+        let abort = AtomicBool::new(false);
+
+        // one O can't fill a 4-wide line, so both are needed for two lines
+        let narrow: BitBoard<4> = BitBoard(0);
+        let solutions: Vec<_> =
+            SolutionIter::new(&[O, O], narrow, false, true, &abort, placeability::always).collect();
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(
+            ColoredBoard::with_placements(narrow, &solutions[0]).to_string(),
+            "OOOO\nOOOO"
+        );
+
+        let wide: BitBoard<12> = BitBoard(0);
+        let mut solutions = vec![];
+        solve_pc(
+            &[I, I, I],
+            wide,
+            false,
+            true,
+            &abort,
+            placeability::hard_drop_only,
+            |soln| solutions.push(soln.to_vec()),
+        );
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(
+            ColoredBoard::with_placements(wide, &solutions[0]).to_string(),
+            "IIIIIIIIIIII"
+        );
+    }
+
+    #[test]
+    fn iterator_can_be_resumed() {
+        let abort = AtomicBool::new(false);
+        let queue = [O, I, I, T];
+        let mut solutions = SolutionIter::new(
+            &queue,
+            two_lines(),
+            true,
+            false,
+            &abort,
+            placeability::simple_srs_spins,
+        );
+        let first = solutions.next_solution().unwrap().to_vec();
+        assert_eq!(solutions.height(), 2);
+        let rest: Vec<_> = solutions.collect();
+        let expected = callback_solutions(&queue, two_lines(), false);
+        assert_eq!(expected[0], first);
+        assert_eq!(expected[1..], rest[..]);
+    }
+
+    fn placement(board: BitBoard, piece: Piece, cells: &str) -> Placement {
+        let cells: BitBoard = cells.parse().unwrap();
+        placeability::placements(board, piece, placeability::always)
+            .into_iter()
+            .map(|(placement, _)| placement)
+            .find(|placement| placement.board() == cells)
+            .unwrap()
+    }
+
+    /// An O and two Is filling `two_lines`, in placement order.
+    fn o_then_is() -> Vec<Placement> {
+        let o = placement(
+            two_lines(),
+            O,
+            "
+            ____OO____
+            ____OO____
+            ",
+        );
+        let on = two_lines().combine(o.board());
+        let bottom = placement(on, I, "______IIII");
+        let top = placement(
+            on.combine(bottom.board()),
+            I,
+            "
+            ______IIII
+            __________
+            ",
+        );
+        vec![o, bottom, top]
+    }
+
+    #[test]
+    fn info_tracks_hold_and_leftover_pieces() {
+        let info = SolutionInfo::from_solution(&[T, O, I, I, S], &o_then_is());
+        assert_eq!(info.holds, vec![true, false, false]);
+        assert_eq!(info.consumed, vec![T, O, I, I]);
+        assert_eq!(info.hold, Some(T));
+        assert_eq!(info.remaining, vec![S]);
+
+        // hold is only pressed once the current piece doesn't fit
+        let info = SolutionInfo::from_solution(&[O, T, I, I], &o_then_is());
+        assert_eq!(info.holds, vec![false, true, false]);
+        assert_eq!(info.hold, Some(T));
+        assert!(info.remaining.is_empty());
+
+        let info = SolutionInfo::new(&[O, I, I, Z], &o_then_is(), false).unwrap();
+        assert_eq!(info.holds, vec![false; 3]);
+        assert_eq!(info.hold, None);
+        assert_eq!(info.remaining, vec![Z]);
+        assert!(SolutionInfo::new(&[T, O, I, I], &o_then_is(), false).is_none());
+        assert!(SolutionInfo::new(&[I, T, O, I], &o_then_is(), true).is_none());
+    }
+
+    #[test]
+    fn solutions_come_with_their_info() {
+        let queue = [T, O, I, I, S];
+        let mut infos = vec![];
+        solve_pc_with_info(
+            &queue,
+            two_lines(),
+            true,
+            false,
+            &AtomicBool::new(false),
+            placeability::always,
+            |info| infos.push(info.clone()),
+        );
+        assert!(!infos.is_empty());
+        for info in infos {
+            assert!(info.holds[0]);
+            assert_eq!(info.hold, Some(T));
+            assert_eq!(info.remaining, vec![S]);
+            assert_eq!(
+                SolutionInfo::new(&queue, &info.placements, true),
+                Some(info)
+            );
+        }
+    }
+
+    fn state_solutions(
+        current: Piece,
+        hold: Option<Piece>,
+        hold_used: bool,
+        preview: &[Piece],
+    ) -> Vec<Vec<Placement>> {
+        let state = GameState {
+            board: two_lines(),
+            current,
+            hold,
+            hold_used,
+            preview: preview.to_vec(),
+        };
+        let mut solutions = vec![];
+        solve_pc_from_state(
+            &state,
+            false,
+            &AtomicBool::new(false),
+            placeability::always,
+            |soln| solutions.push(soln.to_vec()),
+        );
+        solutions
+    }
+
+    #[test]
+    fn solves_from_the_current_and_held_pieces() {
+        // the held O can be swapped in for the T
+        assert!(!state_solutions(T, Some(O), false, &[I, I]).is_empty());
+        assert!(!state_solutions(T, None, false, &[O, I, I]).is_empty());
+        // once hold has been used, the T has to be placed
+        assert!(state_solutions(T, Some(O), true, &[I, I, I]).is_empty());
+
+        let forced = state_solutions(I, Some(O), true, &[I]);
+        assert!(!forced.is_empty());
+        assert!(forced.iter().all(|soln| soln[0].kind.piece() == I));
+        let free = state_solutions(I, Some(O), false, &[I]);
+        assert!(free.iter().any(|soln| soln[0].kind.piece() == O));
+        assert!(state_solutions(T, Some(O), false, &[I, I, I])
+            .iter()
+            .all(|soln| soln[0].kind.piece() != T));
+    }
+
+    #[test]
+    fn multithreaded_state_solve_matches() {
+        let state = GameState {
+            board: two_lines(),
+            current: I,
+            hold: Some(O),
+            hold_used: true,
+            preview: vec![I, T],
+        };
+        let solutions = std::sync::Mutex::new(HashSet::new());
+        solve_pc_mt_from_state(
+            &state,
+            false,
+            &AtomicBool::new(false),
+            placeability::always,
+            |soln| {
+                solutions.lock().unwrap().insert(soln.to_vec());
+            },
+        );
+        let expected: HashSet<_> = state_solutions(I, Some(O), true, &[I, T])
+            .into_iter()
+            .collect();
+        assert!(!expected.is_empty());
+        assert_eq!(solutions.into_inner().unwrap(), expected);
+    }
+
+    #[test]
+    fn all_heights_keeps_going_after_the_first() {
+        let queue = [O, I, I, I, I, I, I, O, I];
+        let heights = |range| {
+            let mut heights = HashSet::new();
+            solve_pc_all_heights(
+                &queue,
+                two_lines(),
+                false,
+                true,
+                &AtomicBool::new(false),
+                range,
+                placeability::always,
+                |soln, height| {
+                    assert!(ColoredBoard::with_placements(two_lines(), soln)
+                        .to_string()
+                        .lines()
+                        .all(|row| !row.contains('_')));
+                    heights.insert(height);
+                },
+            );
+            heights
+        };
+        assert_eq!(heights(0..=6), HashSet::from([2, 4]));
+        assert_eq!(heights(3..=6), HashSet::from([4]));
+        assert!(heights(5..=6).is_empty());
+
+        let mut lowest = vec![];
+        solve_pc(
+            &queue,
+            two_lines(),
+            false,
+            true,
+            &AtomicBool::new(false),
+            placeability::always,
+            |soln| lowest.push(soln.len()),
+        );
+        assert!(lowest.iter().all(|&pieces| pieces == 3));
+    }
+
+    #[test]
+    fn solves_eight_line_pcs() {
+        let board: BitBoard = "XXXXXXXX__\n".repeat(8).parse().unwrap();
+        let mut solutions = vec![];
+        solve_pc_all_heights(
+            &[O, I, O, I, I, O, I, O],
+            board,
+            true,
+            true,
+            &AtomicBool::new(false),
+            8..=8,
+            placeability::simple_srs_spins,
+            |soln, height| {
+                assert_eq!(height, 8);
+                solutions.push(soln.to_vec());
+            },
+        );
+        assert!(!solutions.is_empty());
+        for solution in &solutions {
+            let filled = solution
+                .iter()
+                .fold(board, |board, placement| board.combine(placement.board()));
+            assert_eq!(filled, BitBoard::filled(8));
+        }
+
+        // too tall for the heights `solve_pc` tries
+        let mut found = false;
+        solve_pc(
+            &[O, O, O, O],
+            board,
+            false,
+            true,
+            &AtomicBool::new(false),
+            placeability::always,
+            |_| found = true,
+        );
+        assert!(!found);
+    }
+}
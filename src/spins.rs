@@ -0,0 +1,314 @@
+use crate::kicks::RotationSystem;
+use crate::placeability::{self, Field, Playfield};
+use crate::*;
+
+/// How a piece gets to its final position.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Technique {
+    HardDrop,
+    /// The piece has to be moved after it lands, but doesn't count as a spin.
+    Tuck,
+    TSpin {
+        lines: u32,
+    },
+    TSpinMini {
+        lines: u32,
+    },
+    /// A piece other than T rotated into a position where it can't move left, right or up.
+    AllSpin {
+        lines: u32,
+    },
+}
+
+/// Determines how a placement is done, using the movement search with the given rotation
+/// system and field. T-spins are detected with the 3-corner rule: a T rotated into place is a
+/// T-spin if at least 3 of the cells diagonal to its center are occupied, and a mini if only
+/// one of the two corners it points at is occupied, unless the fifth kick test of a quarter turn
+/// was used.
+///
+/// If a placement can be done both as a spin and without one, it's labelled as a spin. Returns
+/// `None` if the placement can't be reached.
+pub fn classify<const W: usize>(
+    rotation_system: &impl RotationSystem,
+    field: &Field<W>,
+    board: BitBoard<W>,
+    placement: Placement,
+) -> Option<Technique> {
+    let playfield = Playfield::new(board, field);
+    let targets = placement.srs_piece(board);
+    let after = board.combine(placement.board());
+    let lines = (0..MAX_HEIGHT)
+        .filter(|&y| after.line_filled(y) && !board.line_filled(y))
+        .count() as u32;
+
+    let spawn = playfield.spawn(rotation_system, placement.kind.piece());
+    let top = spawn.y.min(playfield.search_top(spawn));
+
+    let mut reached = false;
+    let mut moved_into = false;
+    let mut spin = None;
+    placeability::search(
+        rotation_system,
+        &playfield,
+        placement.kind.piece(),
+        |position, kick| {
+            if targets.contains(&position) {
+                reached = true;
+                match kick {
+                    None => moved_into = true,
+                    Some((from, kick)) => {
+                        let technique = spin_technique(&playfield, position, from, kick, lines);
+                        if spin != Some(Technique::TSpin { lines }) && technique.is_some() {
+                            spin = technique;
+                        }
+                    }
+                }
+            }
+            false
+        },
+    );
+
+    if spin.is_some() {
+        spin
+    } else if !reached {
+        None
+    } else if moved_into
+        && targets
+            .iter()
+            .any(|&t| drops_from_above(&playfield, top, t))
+    {
+        Some(Technique::HardDrop)
+    } else {
+        Some(Technique::Tuck)
+    }
+}
+
+/// Classifies each placement of a solution, placing them one after another starting from
+/// `board`. Returns `None` if any placement can't be reached.
+pub fn classify_solution<const W: usize>(
+    rotation_system: &impl RotationSystem,
+    field: &Field<W>,
+    mut board: BitBoard<W>,
+    solution: &[Placement],
+) -> Option<Vec<Technique>> {
+    let mut result = Vec::with_capacity(solution.len());
+    for &placement in solution {
+        result.push(classify(rotation_system, field, board, placement)?);
+        board = board.combine(placement.board());
+    }
+    Some(result)
+}
+
+fn spin_technique<const W: usize>(
+    playfield: &Playfield<W>,
+    piece: SrsPiece,
+    from: Rotation,
+    kick: usize,
+    lines: u32,
+) -> Option<Technique> {
+    if piece.piece != Piece::T {
+        let immobile = playfield.shifted(piece, -1, 0).is_none()
+            && playfield.shifted(piece, 1, 0).is_none()
+            && playfield.shifted(piece, 0, 1).is_none();
+        return if immobile {
+            Some(Technique::AllSpin { lines })
+        } else {
+            None
+        };
+    }
+
+    let corner = |dx, dy| playfield.occupied(piece.x + dx, piece.y + dy);
+    let corners = [corner(-1, 1), corner(1, 1), corner(1, -1), corner(-1, -1)];
+    if corners.iter().filter(|&&c| c).count() < 3 {
+        return None;
+    }
+
+    // the two corners on the side the T is pointing towards
+    let (a, b) = match piece.rotation {
+        Rotation::North => (0, 1),
+        Rotation::East => (1, 2),
+        Rotation::South => (2, 3),
+        Rotation::West => (3, 0),
+    };
+    // the fifth kick of a quarter turn (e.g. a TST kick) is always a full T-spin; 180 degree
+    // rotations have kick tables of their own, where the fifth kick is nothing special
+    let fifth_kick = kick == 4 && from != piece.rotation.opposite();
+    if corners[a] && corners[b] || fifth_kick {
+        Some(Technique::TSpin { lines })
+    } else {
+        Some(Technique::TSpinMini { lines })
+    }
+}
+
+/// Whether the piece can move straight up from its position to the given height.
+fn drops_from_above<const W: usize>(
+    playfield: &Playfield<W>,
+    top: i32,
+    mut piece: SrsPiece,
+) -> bool {
+    while piece.y < top {
+        match playfield.shifted(piece, 0, 1) {
+            Some(above) => piece = above,
+            None => return false,
+        }
+    }
+    true
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kicks::Srs;
+
+    fn placement(board: BitBoard, piece: Piece, cells: &str) -> Placement {
+        let cells: BitBoard = cells.parse().unwrap();
+        placeability::placements(board, piece, placeability::always)
+            .into_iter()
+            .map(|(placement, _)| placement)
+            .find(|placement| placement.board() == cells)
+            .unwrap()
+    }
+
+    fn technique(board: &str, cells: &str) -> Option<Technique> {
+        let board: BitBoard = board.parse().unwrap();
+        let placement = placement(board, Piece::T, cells);
+        classify(&Srs, &Field::default(), board, placement)
+    }
+
+    #[test]
+    fn counts_corners_for_t_spins() {
+        let tsd = "
+            XXX_______
+            XX___XXXXX
+            XXX_XXXXXX
+        ";
+        assert_eq!(
+            technique(
+                tsd,
+                "
+                __TTT_____
+                ___T______
+                "
+            ),
+            Some(Technique::TSpin { lines: 2 })
+        );
+        // only one of the corners the T points at is filled
+        assert_eq!(
+            technique(
+                tsd,
+                "
+                ___T______
+                __TTT_____
+                __________
+                "
+            ),
+            Some(Technique::TSpinMini { lines: 1 })
+        );
+        // too few corners to count as a spin
+        assert_eq!(
+            technique(
+                "
+                XX________
+                XXX_XXXXXX
+                ",
+                "
+                __TTT_____
+                ___T______
+                "
+            ),
+            Some(Technique::HardDrop)
+        );
+    }
+
+    #[test]
+    fn fifth_kick_makes_a_full_t_spin() {
+        let tst = "
+            XX________
+            X_________
+            X_XXXXXXXX
+            X__XXXXXXX
+            X_XXXXXXXX
+        ";
+        let cells = "
+            _T________
+            _TT_______
+            _T________
+        ";
+        assert_eq!(technique(tst, cells), Some(Technique::TSpin { lines: 3 }));
+
+        // the corner in front of the T is open, which would be a mini without the kick
+        let open_corner = "
+            XX________
+            X_________
+            X_XXXXXXXX
+            X__XXXXXXX
+            X__XXXXXXX
+        ";
+        assert_eq!(
+            technique(open_corner, cells),
+            Some(Technique::TSpin { lines: 2 })
+        );
+    }
+
+    #[test]
+    fn tucks_and_drops_are_not_spins() {
+        let board = "
+            XX________
+            X_________
+            X_XXXXXXXX
+        ";
+        assert_eq!(
+            technique(
+                board,
+                "
+                ___T______
+                __TTT_____
+                __________
+                "
+            ),
+            Some(Technique::HardDrop)
+        );
+        assert_eq!(
+            technique(
+                board,
+                "
+                __T_______
+                _TTT______
+                __________
+                "
+            ),
+            Some(Technique::Tuck)
+        );
+    }
+
+    #[test]
+    fn classifies_each_placement_of_a_solution() {
+        let board: BitBoard = "
+            XXX_______
+            XX___XXXXX
+            XXX_XXXXXX
+        "
+        .parse()
+        .unwrap();
+        let tsd = placement(
+            board,
+            Piece::T,
+            "
+            __TTT_____
+            ___T______
+            ",
+        );
+        let i = placement(
+            board.combine(tsd.board()),
+            Piece::I,
+            "
+            ___IIII___
+            __________
+            __________
+            ",
+        );
+        assert_eq!(
+            classify_solution(&Srs, &Field::default(), board, &[tsd, i]),
+            Some(vec![Technique::TSpin { lines: 2 }, Technique::HardDrop])
+        );
+    }
+}
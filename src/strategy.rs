@@ -0,0 +1,311 @@
+use crate::combination::Tetrominoes;
+use crate::randomizer::{queues, Randomizer};
+use crate::*;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::atomic::AtomicBool;
+
+/// A decision tree for reaching a PC when only part of the queue is visible.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Strategy {
+    /// Probability of getting the PC when following this strategy.
+    pub chance: f64,
+    /// The placement to make now, or `None` if the PC is done or can't be reached.
+    pub placement: Option<Placement>,
+    /// Whether the placement uses the held piece, or holds the current piece and places the next.
+    pub hold: bool,
+    /// What to do next for each possible set of pieces revealed after the placement, with the
+    /// probability they are revealed. If no pieces need to be revealed, there is a single entry
+    /// with no pieces.
+    pub next: Vec<(Vec<Piece>, f64, Strategy)>,
+}
+
+/// Builds the strategy that maximizes the chance of a PC of the given height, knowing only the
+/// visible queue.
+///
+/// `queue` is the current piece followed by the previews. After each placement, one new piece
+/// is revealed for each piece taken from the queue, drawn from `randomizer`, which should be in
+/// the state after dealing the last piece of `queue`. Pieces are only revealed while they could
+/// still be used in the PC.
+///
+/// The tree branches on every piece that could be revealed after every placement, so it grows
+/// exponentially with the number of unknown pieces. States are memoized while searching, but
+/// the returned tree is not shared, and strategies with more than a few unknown pieces can take
+/// a long time and a lot of memory to build.
+#[allow(clippy::too_many_arguments)]
+pub fn pc_strategy<R: Randomizer + Eq + Hash, const W: usize>(
+    board: BitBoard<W>,
+    height: usize,
+    queue: &[Piece],
+    hold: Option<Piece>,
+    hold_allowed: bool,
+    randomizer: &R,
+    abort: &AtomicBool,
+    placeability_judge: impl Fn(BitBoard<W>, Placement) -> bool,
+) -> Strategy {
+    let hold = hold.filter(|_| hold_allowed);
+    let needed = (W * height).saturating_sub(board.0.count_ones() as usize) / Tetrominoes::CELLS;
+
+    // every combination any possible queue could use
+    let mut piece_set: PieceSet = queue.iter().chain(&hold).copied().collect();
+    let unknown = (needed + 1).saturating_sub(queue.len() + hold.is_some() as usize);
+    let mut future = PieceSet::default();
+    for (unknown, _) in queues(randomizer, unknown) {
+        let set: PieceSet = unknown.into_iter().collect();
+        for (max, &count) in future.0.iter_mut().zip(&set.0) {
+            *max = (*max).max(count);
+        }
+    }
+    for (count, &extra) in piece_set.0.iter_mut().zip(&future.0) {
+        *count += extra;
+    }
+    let mut combos = vec![];
+    find_combinations(piece_set, board, abort, height, |combo| {
+        combos.push(combo.to_vec())
+    });
+
+    let mut search = Search {
+        goal: BitBoard::filled(height),
+        combos,
+        hold_allowed,
+        judge: placeability_judge,
+        memo: HashMap::new(),
+    };
+    let state = State {
+        board,
+        hold,
+        queue: queue.to_vec(),
+        randomizer: randomizer.clone(),
+    };
+    search.decide(&state);
+    search.build(&state)
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+struct State<R, const W: usize> {
+    board: BitBoard<W>,
+    hold: Option<Piece>,
+    queue: Vec<Piece>,
+    randomizer: R,
+}
+
+/// The chance of a PC from a state and the best placement, with whether it uses hold.
+type Decision = (f64, Option<(Placement, bool)>);
+
+struct Search<R, J, const W: usize> {
+    goal: BitBoard<W>,
+    combos: Vec<Vec<Placement>>,
+    hold_allowed: bool,
+    judge: J,
+    memo: HashMap<State<R, W>, Decision>,
+}
+
+impl<R: Randomizer + Eq + Hash, J: Fn(BitBoard<W>, Placement) -> bool, const W: usize>
+    Search<R, J, W>
+{
+    /// Chance of a PC from the state, choosing the best placement.
+    fn decide(&mut self, state: &State<R, W>) -> f64 {
+        if state.board == self.goal {
+            return 1.0;
+        }
+        if let Some(&(chance, _)) = self.memo.get(state) {
+            return chance;
+        }
+
+        let mut best = (0.0, None);
+        for (uses_hold, piece, after) in self.options(state) {
+            for placement in self.placements(state.board, piece) {
+                let mut next = after.clone();
+                next.board = state.board.combine(placement.board());
+                let consumed = state.queue.len() - next.queue.len();
+                let chance = self.reveal(next, consumed);
+                if chance > best.0 {
+                    best = (chance, Some((placement, uses_hold)));
+                }
+            }
+        }
+        self.memo.insert(state.clone(), best);
+        best.0
+    }
+
+    /// Expected chance of a PC after revealing `count` more pieces.
+    fn reveal(&mut self, state: State<R, W>, count: usize) -> f64 {
+        if count == 0 || !self.needs_reveal(&state) {
+            return self.decide(&state);
+        }
+        let mut chance = 0.0;
+        for (piece, prob, randomizer) in state.randomizer.next() {
+            let mut next = state.clone();
+            next.queue.push(piece);
+            next.randomizer = randomizer;
+            chance += prob * self.reveal(next, count - 1);
+        }
+        chance
+    }
+
+    /// Whether the pieces after the queue could still be used. Once this is false, it stays
+    /// false for every later state.
+    fn needs_reveal(&self, state: &State<R, W>) -> bool {
+        let remaining = self.goal.remove(state.board).0.count_ones() as usize / Tetrominoes::CELLS;
+        state.queue.len() < remaining + state.hold.is_none() as usize
+    }
+
+    /// The ways to get a piece to place, as whether hold is used, the piece, and the state
+    /// after taking it (with the board not yet updated).
+    fn options(&self, state: &State<R, W>) -> Vec<(bool, Piece, State<R, W>)> {
+        let mut options = vec![];
+        if let Some(&current) = state.queue.first() {
+            let mut next = state.clone();
+            next.queue.remove(0);
+            options.push((false, current, next.clone()));
+            if self.hold_allowed {
+                match state.hold {
+                    Some(held) if held != current => {
+                        next.hold = Some(current);
+                        options.push((true, held, next));
+                    }
+                    None if state.queue.len() >= 2 => {
+                        next.hold = Some(current);
+                        let piece = next.queue.remove(0);
+                        options.push((true, piece, next));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        options
+    }
+
+    /// Placements of the piece that can be made now and that leave a way to finish the PC.
+    fn placements(&self, board: BitBoard<W>, piece: Piece) -> Vec<Placement> {
+        let mut placements = vec![];
+        for combo in &self.combos {
+            // the combination is still possible if each placement is either done or untouched
+            let possible = combo.iter().all(|p| {
+                let left = p.board().remove(board);
+                left == p.board() || left == BitBoard(0)
+            });
+            if !possible {
+                continue;
+            }
+            for &placement in combo {
+                if placement.kind.piece() == piece
+                    && !placement.board().overlaps(board)
+                    && !placements.contains(&placement)
+                    && placement.supported_after_clears(board)
+                    && (self.judge)(board, placement)
+                {
+                    placements.push(placement);
+                }
+            }
+        }
+        placements
+    }
+
+    fn build(&mut self, state: &State<R, W>) -> Strategy {
+        let chance = self.decide(state);
+        let best = self.memo.get(state).and_then(|&(_, best)| best);
+        let mut strategy = Strategy {
+            chance,
+            placement: best.map(|(placement, _)| placement),
+            hold: best.is_some_and(|(_, hold)| hold),
+            next: vec![],
+        };
+        if let Some((placement, uses_hold)) = best {
+            let (_, _, mut next) = self
+                .options(state)
+                .into_iter()
+                .find(|&(hold, _, _)| hold == uses_hold)
+                .unwrap();
+            next.board = state.board.combine(placement.board());
+            let consumed = state.queue.len() - next.queue.len();
+            let mut revealed = vec![];
+            self.revealed_states(next, consumed, vec![], 1.0, &mut revealed);
+            for (pieces, prob, next) in revealed {
+                let child = self.build(&next);
+                strategy.next.push((pieces, prob, child));
+            }
+        }
+        strategy
+    }
+
+    fn revealed_states(
+        &self,
+        state: State<R, W>,
+        count: usize,
+        pieces: Vec<Piece>,
+        prob: f64,
+        into: &mut Vec<(Vec<Piece>, f64, State<R, W>)>,
+    ) {
+        if count == 0 || !self.needs_reveal(&state) {
+            into.push((pieces, prob, state));
+            return;
+        }
+        for (piece, p, randomizer) in state.randomizer.next() {
+            let mut next = state.clone();
+            next.queue.push(piece);
+            next.randomizer = randomizer;
+            let mut pieces = pieces.clone();
+            pieces.push(piece);
+            self.revealed_states(next, count - 1, pieces, prob * p, into);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::randomizer::{Memoryless, SevenBag};
+    use Piece::*;
+
+    fn two_lines() -> BitBoard {
+        "
+        XXXX______
+        XXXX______
+        "
+        .parse()
+        .unwrap()
+    }
+
+    fn plan<R: Randomizer + Eq + Hash>(queue: &[Piece], randomizer: &R) -> Strategy {
+        pc_strategy(
+            two_lines(),
+            2,
+            queue,
+            None,
+            true,
+            randomizer,
+            &AtomicBool::new(false),
+            placeability::always,
+        )
+    }
+
+    #[test]
+    fn known_queue_always_clears() {
+        let strategy = plan(&[O, I, I], &Memoryless);
+        assert!((strategy.chance - 1.0).abs() < 1e-9);
+        assert!(strategy.placement.is_some());
+    }
+
+    #[test]
+    fn waits_for_the_missing_piece() {
+        // the PC needs another I, and holding any other piece gives it a second chance to come
+        let strategy = plan(&[O, I], &Memoryless);
+        assert!((strategy.chance - 13.0 / 49.0).abs() < 1e-9);
+        assert_eq!(strategy.next.len(), 7);
+        let total: f64 = strategy.next.iter().map(|(_, prob, _)| prob).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        for (pieces, _, next) in &strategy.next {
+            let expected = if pieces == &[I] { 1.0 } else { 1.0 / 7.0 };
+            assert!((next.chance - expected).abs() < 1e-9);
+        }
+
+        // without another I, the I has to be placed first and the next two pieces have to be
+        // J and L, which are 2 of the 20 pairs the rest of the bag can deal
+        let bag = SevenBag::default().after(&[O, I]).unwrap();
+        let strategy = plan(&[O, I], &bag);
+        assert!((strategy.chance - 0.1).abs() < 1e-9);
+        assert!(strategy.hold);
+        assert_eq!(strategy.placement.unwrap().kind.piece(), I);
+    }
+}
@@ -0,0 +1,254 @@
+use crate::*;
+
+/// The contents of a single cell of a `ColoredBoard`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Cell {
+    Empty,
+    Garbage,
+    Piece(Piece),
+}
+
+impl Cell {
+    fn from_char(c: char) -> Option<Cell> {
+        Some(match c {
+            '_' => Cell::Empty,
+            'X' => Cell::Garbage,
+            'S' => Cell::Piece(Piece::S),
+            'Z' => Cell::Piece(Piece::Z),
+            'J' => Cell::Piece(Piece::J),
+            'L' => Cell::Piece(Piece::L),
+            'T' => Cell::Piece(Piece::T),
+            'O' => Cell::Piece(Piece::O),
+            'I' => Cell::Piece(Piece::I),
+            _ => return None,
+        })
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            Cell::Empty => '_',
+            Cell::Garbage => 'X',
+            Cell::Piece(p) => ['S', 'Z', 'J', 'L', 'T', 'O', 'I'][p as usize],
+        }
+    }
+}
+
+/// A board of `W` columns that remembers which piece filled each cell, for displaying solutions.
+///
+/// The text format has one line of `W` characters per row, top row first, with one character per
+/// cell: `_` for empty cells, `X` for garbage and the piece letter for cells filled by a piece.
+/// Rows past `BitBoard::<W>::HEIGHT` are never used. For example, the following is a PCO setup
+/// on a standard board:
+///
+/// ```text
+/// XXXX____XX
+/// XXXX___XXX
+/// XXXX__XXXX
+/// XXXX___XXX
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ColoredBoard<const W: usize = 10>(pub [[Cell; W]; MAX_HEIGHT]);
+
+impl<const W: usize> ColoredBoard<W> {
+    /// Creates a colored board with the filled cells of `board` as garbage.
+    pub fn new(board: BitBoard<W>) -> Self {
+        let mut colored = ColoredBoard::default();
+        colored.draw(board, Cell::Garbage);
+        colored
+    }
+
+    /// Creates a colored board showing a solution or combination on top of `board`.
+    pub fn with_placements(board: BitBoard<W>, placements: &[Placement]) -> Self {
+        let mut colored = ColoredBoard::new(board);
+        for &placement in placements {
+            colored.draw(placement.board(), Cell::Piece(placement.kind.piece()));
+        }
+        colored
+    }
+
+    /// Sets every cell filled in `board` to `cell`.
+    pub fn draw(&mut self, board: BitBoard<W>, cell: Cell) {
+        for y in 0..BitBoard::<W>::HEIGHT {
+            for x in 0..W {
+                if board.cell_filled(x, y) {
+                    self.0[y][x] = cell;
+                }
+            }
+        }
+    }
+
+    /// Returns the board with every non-empty cell filled.
+    pub fn board(&self) -> BitBoard<W> {
+        let mut board = 0;
+        for y in 0..BitBoard::<W>::HEIGHT {
+            for x in 0..W {
+                if self.0[y][x] != Cell::Empty {
+                    board |= 1 << (x + W * y);
+                }
+            }
+        }
+        BitBoard(board)
+    }
+}
+
+impl<const W: usize> Default for ColoredBoard<W> {
+    fn default() -> Self {
+        ColoredBoard([[Cell::Empty; W]; MAX_HEIGHT])
+    }
+}
+
+impl<const W: usize> std::fmt::Display for ColoredBoard<W> {
+    /// Writes the rows from the highest non-empty row down, or a single empty row if the board
+    /// is empty.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let height = (1..MAX_HEIGHT)
+            .rev()
+            .find(|&y| self.0[y].iter().any(|&c| c != Cell::Empty))
+            .unwrap_or(0)
+            + 1;
+        for y in (0..height).rev() {
+            for &cell in &self.0[y] {
+                write!(f, "{}", cell.to_char())?;
+            }
+            if y != 0 {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<const W: usize> std::str::FromStr for ColoredBoard<W> {
+    type Err = ParseBoardError;
+
+    /// Parses the text format. Leading and trailing whitespace on each line and blank lines are
+    /// ignored; the last line is the bottom row of the board.
+    fn from_str(s: &str) -> Result<Self, ParseBoardError> {
+        let rows: Vec<_> = s.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+        if rows.len() > BitBoard::<W>::HEIGHT {
+            return Err(ParseBoardError);
+        }
+        let mut this = ColoredBoard::default();
+        for (y, row) in rows.iter().rev().enumerate() {
+            if row.chars().count() != W {
+                return Err(ParseBoardError);
+            }
+            for (x, c) in row.chars().enumerate() {
+                this.0[y][x] = Cell::from_char(c).ok_or(ParseBoardError)?;
+            }
+        }
+        Ok(this)
+    }
+}
+
+impl<const W: usize> std::fmt::Display for BitBoard<W> {
+    /// Writes the board in the text format described on `ColoredBoard`, using `X` for filled
+    /// cells.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        ColoredBoard::new(*self).fmt(f)
+    }
+}
+
+impl<const W: usize> std::str::FromStr for BitBoard<W> {
+    type Err = ParseBoardError;
+
+    /// Parses the text format described on `ColoredBoard`. Piece letters count as filled cells.
+    fn from_str(s: &str) -> Result<Self, ParseBoardError> {
+        Ok(s.parse::<ColoredBoard<W>>()?.board())
+    }
+}
+
+/// Error returned when parsing a board from text fails.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ParseBoardError;
+
+impl std::fmt::Display for ParseBoardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "expected no more rows than the board holds, each with a cell per column that is one \
+             of `_`, `X` or a piece letter"
+        )
+    }
+}
+
+impl std::error::Error for ParseBoardError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rows_top_first() {
+        let board: BitBoard = "
+            XX____XXXX
+            XXX___XXXX
+            XXXX__XXXX
+            XXX___XXXX
+        "
+        .parse()
+        .unwrap();
+        assert_eq!(
+            board,
+            BitBoard(0b1111000011_1111000111_1111001111_1111000111)
+        );
+        assert_eq!(
+            board.to_string(),
+            "XX____XXXX\nXXX___XXXX\nXXXX__XXXX\nXXX___XXXX"
+        );
+        assert_eq!(BitBoard::<10>(0).to_string(), "__________");
+    }
+
+    #[test]
+    fn other_widths() {
+        let board: BitBoard<6> = "XX____\nXXX___".parse().unwrap();
+        assert_eq!(board, BitBoard(0b000011_000111));
+        assert_eq!(board.to_string(), "XX____\nXXX___");
+        assert_eq!("XX____XXXX".parse::<BitBoard<6>>(), Err(ParseBoardError));
+
+        let row = "X___________\n";
+        assert!(row.repeat(10).parse::<BitBoard<12>>().is_ok());
+        assert_eq!(row.repeat(11).parse::<BitBoard<12>>(), Err(ParseBoardError));
+    }
+
+    #[test]
+    fn rejects_bad_boards() {
+        assert_eq!("XX____XXX".parse::<BitBoard>(), Err(ParseBoardError));
+        assert_eq!("XX____XXXA".parse::<BitBoard>(), Err(ParseBoardError));
+        let row = "X_________\n";
+        assert!(row.repeat(MAX_HEIGHT).parse::<BitBoard>().is_ok());
+        assert_eq!(
+            row.repeat(MAX_HEIGHT + 1).parse::<BitBoard>(),
+            Err(ParseBoardError)
+        );
+    }
+
+    #[test]
+    fn colored_round_trip() {
+        let text = "TTT_______\nXTXXXXXXXI";
+        let colored: ColoredBoard = text.parse().unwrap();
+        assert_eq!(colored.0[1][1], Cell::Piece(Piece::T));
+        assert_eq!(colored.0[0][0], Cell::Garbage);
+        assert_eq!(colored.0[0][9], Cell::Piece(Piece::I));
+        assert_eq!(colored.to_string(), text);
+        assert_eq!(colored.board().to_string(), "XXX_______\nXXXXXXXXXX");
+    }
+
+    #[test]
+    fn shows_placements() {
+        let abort = std::sync::atomic::AtomicBool::new(false);
+        let mut solution = vec![];
+        solve_pc(
+            &[Piece::O; 5],
+            BitBoard::<10>(0),
+            false,
+            true,
+            &abort,
+            placeability::hard_drop_only,
+            |soln| solution = soln.to_vec(),
+        );
+        let colored = ColoredBoard::<10>::with_placements(BitBoard(0), &solution);
+        assert_eq!(colored.to_string(), "OOOOOOOOOO\nOOOOOOOOOO");
+        assert_eq!(colored.board(), BitBoard::filled(2));
+    }
+}